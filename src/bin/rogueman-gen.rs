@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+use rogueman::scenes::dungeon_manager::Manager;
+use rogueman::scenes::room_gen::config::{GenConfig, PRESETS};
+use rogueman::scenes::room_gen::export::{manager_ascii, manager_image};
+use rogueman::scenes::room_gen::generators::{Generator, BSP, GENERATORS};
+use rogueman::scenes::room_gen::rng::random_seed;
+
+const USAGE: &str = "Usage: rogueman-gen [options]
+
+Generates dungeons without opening the game, prints them as ASCII when no output is given
+
+Options:
+    --seed N        Seed of the first run (random by default)
+    --count N       Amount of runs to generate, with seeds counting up from --seed (default 1)
+    --floors N      Floors generated per run (default 1)
+    --preset NAME   Config of the first floor: small, medium or huge (default medium)
+    --generator G   Generator of the first floor: bsp, caves, tunnels or stitcher (default bsp),
+                    deeper floors cycle through the others
+    --scale N       Pixels per tile in the image (default 4)
+    --out FILE      Writes a PNG image of every floor side by side
+    --ascii FILE    Writes the annotated ASCII map
+    --help          Shows this message
+
+With --count above 1 the seed is added to the file names (map.png becomes map-SEED.png)";
+
+struct Args {
+    seed: u64,
+    count: u64,
+    floors: usize,
+    preset: usize,
+    generator: Generator,
+    scale: usize,
+    out: Option<String>,
+    ascii: Option<String>,
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    exit(1)
+}
+
+/// Stops on errors that aren't about the arguments
+fn abort(message: &str) -> ! {
+    eprintln!("{}", message);
+    exit(1)
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        seed: random_seed(),
+        count: 1,
+        floors: 1,
+        preset: 1,
+        generator: BSP,
+        scale: 4,
+        out: None,
+        ascii: None,
+    };
+
+    let mut raw = std::env::args().skip(1);
+    while let Some(flag) = raw.next() {
+        if flag == "--help" {
+            println!("{}", USAGE);
+            exit(0);
+        }
+
+        let value = raw
+            .next()
+            .unwrap_or_else(|| fail(&format!("Missing value for {}", flag)));
+        let number = || {
+            value
+                .parse::<u64>()
+                .unwrap_or_else(|_| fail(&format!("Invalid number for {}: {}", flag, value)))
+        };
+
+        match flag.as_str() {
+            "--seed" => args.seed = number(),
+            "--count" => args.count = number().max(1),
+            "--floors" => args.floors = number().max(1) as usize,
+            "--scale" => args.scale = number().max(1) as usize,
+            "--preset" => {
+                args.preset = PRESETS
+                    .iter()
+                    .position(|preset| preset.name.eq_ignore_ascii_case(&value))
+                    .unwrap_or_else(|| fail(&format!("Unknown preset: {}", value)))
+            }
+            "--generator" => {
+                args.generator = *GENERATORS
+                    .iter()
+                    .find(|generator| generator.name().eq_ignore_ascii_case(&value))
+                    .unwrap_or_else(|| fail(&format!("Unknown generator: {}", value)))
+            }
+            "--out" => args.out = Some(value),
+            "--ascii" => args.ascii = Some(value),
+            _ => fail(&format!("Unknown option: {}", flag)),
+        }
+    }
+
+    args
+}
+
+/// Creates the directories leading to `path`, `Image::export_png` panics when they are missing
+fn create_parent(path: &str) {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)
+            .unwrap_or_else(|err| abort(&format!("Couldn't create {}: {}", parent.display(), err)));
+    }
+}
+
+/// `map.png` becomes `map-SEED.png` when generating more than one run
+fn file_name(path: &str, seed: u64, count: u64) -> String {
+    if count == 1 {
+        return path.to_owned();
+    }
+    match path.rsplit_once('.') {
+        Some((name, extension)) => format!("{}-{}.{}", name, seed, extension),
+        None => format!("{}-{}", path, seed),
+    }
+}
+
+fn main() {
+    let args = parse_args();
+
+    for seed in (0..args.count).map(|run| args.seed.wrapping_add(run)) {
+        let config = GenConfig {
+            generator: args.generator,
+            ..PRESETS[args.preset]
+        };
+        let mut manager = Manager::new(seed, config)
+            .unwrap_or_else(|err| abort(&format!("Couldn't generate seed {}: {}", seed, err)));
+        for _ in 1..args.floors {
+            if let Err(err) = manager.next_floor() {
+                let depth = manager.depth + 1;
+                abort(&format!(
+                    "Couldn't generate seed {} at depth {}: {}",
+                    seed, depth, err
+                ));
+            }
+        }
+
+        if let Some(out) = &args.out {
+            let path = file_name(out, seed, args.count);
+            let image = manager_image(&manager, args.scale)
+                .unwrap_or_else(|err| abort(&format!("Couldn't draw seed {}: {}", seed, err)));
+            create_parent(&path);
+            image.export_png(&path);
+            println!("Wrote {}", path);
+        }
+
+        let ascii = manager_ascii(&manager);
+        match &args.ascii {
+            Some(out) => {
+                let path = file_name(out, seed, args.count);
+                create_parent(&path);
+                fs::write(&path, ascii)
+                    .unwrap_or_else(|err| abort(&format!("Couldn't write {}: {}", path, err)));
+                println!("Wrote {}", path);
+            }
+            None if args.out.is_none() => println!("{}", ascii),
+            None => {}
+        }
+    }
+}
@@ -66,6 +66,15 @@ impl Camera {
         }
     }
 
+    /// World position shown at the screen position `screen`
+    pub fn world_pos(&self, screen: Vec2) -> Vec2 {
+        let mut offset = self.camera.target - vec2(screen_width(), screen_height()) / 2.0;
+        if self.shake.is_some() {
+            offset += self.shake_offset;
+        }
+        screen + offset
+    }
+
     pub fn set_shake(&mut self, shake: ShakeConfig) {
         self.shake = Some(shake);
         self.shake_start = get_time();
@@ -0,0 +1,6 @@
+#![allow(dead_code, unused_imports)]
+
+pub mod camera;
+pub mod scenes;
+pub mod spritesheet;
+pub mod util;
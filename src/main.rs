@@ -1,15 +1,8 @@
-#![allow(dead_code, unused_imports)]
-
-mod camera;
-mod scenes;
-mod spritesheet;
-mod util;
-
 use macroquad::prelude::{next_frame, Conf};
-use scenes::game::GameScene;
-
-use crate::scenes::game::GAME;
-use crate::scenes::object::Object;
+use rogueman::scenes::game::GameScene;
+use rogueman::scenes::menus::MainMenu;
+use rogueman::scenes::object::Object;
+use rogueman::scenes::scene::SceneStack;
 
 fn config() -> Conf {
     Conf {
@@ -24,10 +17,11 @@ fn config() -> Conf {
 #[macroquad::main(config)]
 async fn main() {
     GameScene::init().await;
+    let mut scenes = SceneStack::new(Box::new(MainMenu::new()));
 
     loop {
-        GAME().update();
-        GAME().draw();
+        scenes.update();
+        scenes.draw();
 
         next_frame().await;
     }
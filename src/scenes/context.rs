@@ -0,0 +1,246 @@
+use derive_new::new;
+use macroquad::prelude::{
+    get_frame_time, get_time, is_key_down, is_key_pressed, is_mouse_button_down,
+    is_mouse_button_pressed, mouse_position, vec2, KeyCode, MouseButton, Vec2,
+};
+
+use super::dungeon_manager::{Floor, Manager};
+use super::entities::components::Bundle;
+use super::entities::{Entities, Entity};
+use super::objects::door::Door;
+use super::objects::shapes::line::Line;
+use super::objects::shapes::rect::Rect;
+use super::room_gen::gen::{load_tiles, load_walls, tile_rect, Objects as RoomObjects};
+use crate::camera::{Camera, ShakeConfig};
+use crate::util::{NUMBER_KEYS, SQUARE_SIZE};
+
+/// The level entities move around in
+pub struct World {
+    pub manager: Manager,
+    /// Tiles that can't be walked through
+    pub walls: Vec<Rect>,
+    /// Every tile that gets drawn
+    pub tiles: Vec<(Rect, RoomObjects)>,
+    pub doors: Vec<Door>,
+}
+impl World {
+    pub fn new(manager: Manager) -> World {
+        let mut world = World {
+            manager,
+            walls: vec![],
+            tiles: vec![],
+            doors: vec![],
+        };
+        world.load_tiles();
+        world
+    }
+
+    pub fn floor(&self) -> &Floor {
+        self.manager.floor()
+    }
+
+    /// Rebuilds `walls` and `tiles` from the current floor
+    pub fn load_tiles(&mut self) {
+        let room = &self.manager.floor().room;
+        self.walls = load_walls(room);
+        self.tiles = load_tiles(room);
+    }
+
+    /// Walls and closed doors around `rect`, cheaper than going through every wall for entities
+    /// that only move a bit every frame
+    pub fn walls_touching(&self, rect: &Rect) -> Vec<Rect> {
+        let floor = self.floor();
+        let tiles = floor.tiles_touching(rect).into_iter();
+        let walls = tiles
+            .filter(|(x, y)| !floor.room[*y][*x].properties().walkable)
+            .map(|(x, y)| {
+                let pos = vec2(x as f32, y as f32) * SQUARE_SIZE;
+                Rect::new(pos.x, pos.y, SQUARE_SIZE, SQUARE_SIZE)
+            });
+        walls.chain(self.closed_doors().copied()).collect()
+    }
+
+    /// Whether nothing that blocks sight (walls, crates, closed doors) is between `from` and `to`
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        if from == to {
+            return true;
+        }
+
+        let floor = self.floor();
+        let mut line = Line::new(from, to, 1.0);
+        let bounds = Rect::new(
+            from.x.min(to.x),
+            from.y.min(to.y),
+            (from.x - to.x).abs(),
+            (from.y - to.y).abs(),
+        );
+        let mut blocking = (floor.tiles_touching(&bounds).into_iter())
+            .filter(|(x, y)| floor.room[*y][*x].properties().blocks_sight)
+            .map(|(x, y)| tile_rect(x, y))
+            .chain(self.closed_doors().copied());
+        !blocking.any(|rect| line.touches_rect(&rect))
+    }
+
+    /// Walls the player can't currently walk through, the closed doors
+    pub fn closed_doors(&self) -> impl Iterator<Item = &Rect> {
+        self.doors
+            .iter()
+            .filter(|door| !door.open)
+            .map(|door| &door.rect)
+    }
+}
+
+/// Change to the run asked for by an entity while it updates, applied by `GameScene` once every
+/// entity is done updating
+pub enum Command {
+    /// Spawns an entity with a reserved id (see `Context::spawn`)
+    Spawn(Entity, Box<Bundle>),
+    Despawn(Entity),
+    /// Damages an entity with `Health`
+    Hit {
+        entity: Entity,
+        damage: f32,
+    },
+    HitPlayer(f32),
+    /// Turns the tile into floor, for crates and secret walls that got shot
+    BreakTile(usize, usize),
+    Shake(ShakeConfig),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Time {
+    /// Seconds since the game started
+    pub now: f64,
+    /// Seconds since the last frame
+    pub delta: f32,
+}
+impl Time {
+    pub fn frame() -> Time {
+        Time {
+            now: get_time(),
+            delta: get_frame_time(),
+        }
+    }
+}
+
+/// Input of the frame, only the keys and buttons entities look at are captured
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Input {
+    /// World position of the mouse
+    pub mouse: Vec2,
+    pub keys_down: Vec<KeyCode>,
+    pub keys_pressed: Vec<KeyCode>,
+    pub buttons_down: Vec<MouseButton>,
+    pub buttons_pressed: Vec<MouseButton>,
+}
+impl Input {
+    const KEYS: [KeyCode; 6] = [
+        KeyCode::W,
+        KeyCode::A,
+        KeyCode::S,
+        KeyCode::D,
+        KeyCode::Space,
+        KeyCode::G,
+    ];
+    const BUTTONS: [MouseButton; 2] = [MouseButton::Left, MouseButton::Right];
+
+    pub fn capture(camera: &Camera) -> Input {
+        let keys = Input::KEYS.iter().chain(NUMBER_KEYS.iter()).copied();
+        let mouse = mouse_position();
+        Input {
+            mouse: camera.world_pos(vec2(mouse.0, mouse.1)),
+            keys_down: keys.clone().filter(|key| is_key_down(*key)).collect(),
+            keys_pressed: keys.filter(|key| is_key_pressed(*key)).collect(),
+            buttons_down: Input::BUTTONS
+                .into_iter()
+                .filter(|button| is_mouse_button_down(*button))
+                .collect(),
+            buttons_pressed: Input::BUTTONS
+                .into_iter()
+                .filter(|button| is_mouse_button_pressed(*button))
+                .collect(),
+        }
+    }
+
+    pub fn key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+}
+
+/// What an entity gets to see while it updates or draws. It can only change itself, anything
+/// else goes through `commands`
+#[derive(new)]
+pub struct Context<'a> {
+    pub world: &'a World,
+    /// Where the player is, a copy while the player itself updates
+    pub player: &'a Rect,
+    pub entities: &'a Entities,
+    pub input: &'a Input,
+    pub time: Time,
+    pub commands: &'a mut Vec<Command>,
+}
+impl Context<'_> {
+    /// Asks for an entity to be spawned, its id can be used right away
+    pub fn spawn(&mut self, bundle: Bundle) -> Entity {
+        let entity = self.entities.reserve();
+        self.commands.push(Command::Spawn(entity, Box::new(bundle)));
+        entity
+    }
+}
+
+#[test]
+fn entities_only_change_themselves() {
+    use super::entities::Fixture;
+    use super::objects::bullet::{bullet, BulletConfig};
+    use super::objects::enemies::enemy::enemy;
+    use super::objects::enemies::kinds::ENEMIES;
+
+    let Fixture { world, spawn, .. } = Fixture::new();
+    let mut entities = Entities::default();
+    let enemy = entities.spawn(enemy(&ENEMIES[0], spawn, None));
+    let config = BulletConfig {
+        speed: 0.0,
+        ..Fixture::bullet()
+    };
+    let bullet = entities.spawn(bullet(0.0, spawn, config, 0.0));
+
+    let player = Rect::new(0.0, 0.0, 1.0, 1.0);
+    let time = Time {
+        now: 0.5,
+        delta: 0.1,
+    };
+    let mut commands = vec![];
+    entities.update(&world, &player, time, &mut commands);
+
+    // The systems only ask for the hit, the enemy is only hit once the commands are applied
+    assert_eq!(entities.len(), 2);
+    assert_eq!(
+        entities.healths.get(enemy).unwrap().current,
+        ENEMIES[0].max_health
+    );
+    assert!(matches!(
+        commands[..],
+        [Command::Hit { entity, damage }, Command::Despawn(despawned)]
+            if entity == enemy && damage == 4.0 && despawned == bullet
+    ));
+
+    entities.apply(&mut commands, time.now);
+    assert!(commands.is_empty());
+    assert!(!entities.is_alive(bullet));
+    assert_eq!(
+        entities.healths.get(enemy).unwrap().current,
+        ENEMIES[0].max_health - 4.0
+    );
+}
@@ -1,7 +1,196 @@
-use super::room_gen::gen::Objects;
+use macroquad::prelude::Vec2;
 
-#[derive(Clone)]
-pub struct Manager {
+use super::objects::shapes::rect::Rect;
+use super::room_gen::config::{GenConfig, SCALING};
+use super::room_gen::error::GenError;
+use super::room_gen::gen::{generate_room, tile_rect, Objects};
+use super::room_gen::graph::RoomGraph;
+use super::room_gen::post::Room;
+use crate::util::{tile_center, SQUARE_SIZE};
+
+/// A single generated level of the dungeon
+#[derive(Clone, Debug, PartialEq)]
+pub struct Floor {
     pub room: Vec<Vec<Objects>>,
+    /// Door tiles, they are left open (`Objects::Door`) in `room`
     pub doors: Vec<(usize, usize)>,
+    /// Indexes (in `doors`) of the doors of each room in `rects`
+    pub room_doors: Vec<Vec<usize>>,
+    /// Room outlines (in tiles)
+    pub rects: Vec<Rect>,
+    /// Type of each room in `rects`
+    pub room_types: Vec<Room>,
+    /// Connections between the rooms in `rects`
+    pub graph: RoomGraph,
+    /// Tile of the stairs to the next floor, in the end room
+    pub stairs: Option<(usize, usize)>,
+    /// Tile the player starts on, in the start room
+    pub player_spawn: Option<(usize, usize)>,
+    /// Tiles enemies can spawn on for each room in `rects`, clear of walls and obstacles
+    pub enemy_spawns: Vec<Vec<(usize, usize)>>,
+    /// Seed the floor was generated from
+    pub seed: u64,
+}
+impl Floor {
+    /// Index (in `rects`) of the room containing the tile at `x`, `y`
+    pub fn room_index(&self, x: usize, y: usize) -> Option<usize> {
+        let point = Vec2::new(x as f32, y as f32);
+        self.rects
+            .iter()
+            .position(|rect| rect.touches_point(&point))
+    }
+
+    /// Index (in `rects`) of the room containing the world position `pos`
+    pub fn room_index_at(&self, pos: Vec2) -> Option<usize> {
+        if pos.x < 0.0 || pos.y < 0.0 {
+            return None;
+        }
+        self.room_index(
+            (pos.x / SQUARE_SIZE) as usize,
+            (pos.y / SQUARE_SIZE) as usize,
+        )
+    }
+
+    /// World space rect of the inside (without walls) of a room
+    pub fn room_interior(&self, index: usize) -> Rect {
+        let rect = self.rects[index];
+        Rect::new(
+            (rect.pos.x + 1.0) * SQUARE_SIZE,
+            (rect.pos.y + 1.0) * SQUARE_SIZE,
+            (rect.width - 1.0) * SQUARE_SIZE,
+            (rect.height - 1.0) * SQUARE_SIZE,
+        )
+    }
+
+    /// Type of the room at the world position `pos`, `None` when in a corridor or outside the map
+    pub fn room_type_at(&self, pos: Vec2) -> Option<Room> {
+        self.room_index_at(pos).map(|index| self.room_types[index])
+    }
+
+    pub fn start_room(&self) -> Option<usize> {
+        self.room_types.iter().position(|t| t == &Room::Start)
+    }
+
+    pub fn end_room(&self) -> Option<usize> {
+        self.room_types.iter().position(|t| t == &Room::End)
+    }
+
+    /// Rooms that can't be avoided on the way from the start room to the end room
+    pub fn critical_path(&self) -> Vec<usize> {
+        match (self.start_room(), self.end_room()) {
+            (Some(start), Some(end)) => self.graph.critical_path(start, end),
+            _ => vec![],
+        }
+    }
+
+    /// Amount of rooms between the start room and `room`, `None` if it can't be reached
+    pub fn start_distance(&self, room: usize) -> Option<usize> {
+        self.graph.distance(self.start_room()?, room)
+    }
+
+    /// World position the player spawns at
+    pub fn player_position(&self) -> Vec2 {
+        match self.player_spawn {
+            Some(tile) => tile_center(tile),
+            None => self.room_interior(0).get_center(),
+        }
+    }
+
+    /// World space rect of the stairs
+    pub fn stairs_rect(&self) -> Option<Rect> {
+        self.stairs.map(|(x, y)| tile_rect(x, y))
+    }
+
+    /// Tile at the world position `pos`, outside the map is all wall
+    pub fn tile_at(&self, pos: Vec2) -> Objects {
+        if pos.x < 0.0 || pos.y < 0.0 {
+            return Objects::Wall;
+        }
+        let (x, y) = (
+            (pos.x / SQUARE_SIZE) as usize,
+            (pos.y / SQUARE_SIZE) as usize,
+        );
+        self.room
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(Objects::Wall)
+    }
+
+    /// Tiles (in the map) overlapped by the world space `rect`
+    pub fn tiles_touching(&self, rect: &Rect) -> Vec<(usize, usize)> {
+        let to_tile = |value: f32| (value.max(0.0) / SQUARE_SIZE) as usize;
+        let (left, top) = (to_tile(rect.get_left()), to_tile(rect.get_top()));
+        let (right, bottom) = (to_tile(rect.get_right()), to_tile(rect.get_bottom()));
+
+        (top..=bottom.min(self.room.len().saturating_sub(1)))
+            .flat_map(|y| {
+                (left..=right.min(self.room[y].len().saturating_sub(1))).map(move |x| (x, y))
+            })
+            .collect()
+    }
+}
+
+/// The floors of a run
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manager {
+    /// Seed of the run, the seed of every floor is derived from it
+    pub seed: u64,
+    /// Config of the first floor, deeper floors are scaled from it with `SCALING`
+    pub config: GenConfig,
+    /// Floors generated so far, in order
+    pub floors: Vec<Floor>,
+    /// Index of the current floor in `floors`
+    pub depth: usize,
+}
+impl Manager {
+    pub fn new(seed: u64, config: GenConfig) -> Result<Manager, GenError> {
+        Ok(Manager {
+            seed,
+            config,
+            floors: vec![generate_room(Manager::floor_seed(seed, 0), &config)?],
+            depth: 0,
+        })
+    }
+
+    /// Seed of the floor at `depth` for the run with `seed`
+    pub fn floor_seed(seed: u64, depth: usize) -> u64 {
+        seed.wrapping_add((depth as u64).wrapping_mul(0x2545_F491_4F6C_DD1D))
+    }
+
+    pub fn floor(&self) -> &Floor {
+        &self.floors[self.depth]
+    }
+
+    pub fn floor_mut(&mut self) -> &mut Floor {
+        &mut self.floors[self.depth]
+    }
+
+    /// Moves down to the next floor, generating it if needed. Stays on the current floor if the
+    /// next one can't be generated
+    pub fn next_floor(&mut self) -> Result<&Floor, GenError> {
+        let depth = self.depth + 1;
+        if depth >= self.floors.len() {
+            let config = SCALING.config(&self.config, depth);
+            let seed = Manager::floor_seed(self.seed, depth);
+            self.floors.push(generate_room(seed, &config)?);
+        }
+        self.depth = depth;
+        Ok(self.floor())
+    }
+}
+
+#[test]
+fn floors() {
+    use super::room_gen::config::SMALL;
+
+    let mut a = Manager::new(5, SMALL).unwrap();
+    let mut b = Manager::new(5, SMALL).unwrap();
+    a.next_floor().unwrap();
+    b.next_floor().unwrap();
+
+    assert_eq!(a, b);
+    assert_eq!(a.depth, 1);
+    assert!(a.floors[1].room.len() > a.floors[0].room.len());
+    assert!(a.floors.iter().all(|floor| floor.stairs.is_some()));
 }
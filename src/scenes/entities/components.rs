@@ -0,0 +1,191 @@
+use macroquad::prelude::{Color, Vec2};
+
+use super::Entity;
+use crate::scenes::objects::enemies::kinds::EnemyKind;
+use crate::scenes::objects::shapes::rect::Rect;
+use crate::util::DAMAGE_COOLDOWN;
+
+/// Components of an entity about to be spawned. A kind of entity is a function building one (see
+/// `bullet::bullet` and `enemy::enemy`), with `None` for the components it doesn't have
+#[derive(Clone, Debug, Default)]
+pub struct Bundle {
+    pub rect: Option<Rect>,
+    pub health: Option<Health>,
+    pub velocity: Option<Velocity>,
+    pub sprite: Option<Sprite>,
+    pub faction: Option<Faction>,
+    pub projectile: Option<Projectile>,
+    pub enemy: Option<Enemy>,
+    pub target: Option<Target>,
+    pub collider: Option<Collider>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    pub last_damage: f64,
+}
+impl Health {
+    pub fn new(max: f32) -> Health {
+        Health {
+            current: max,
+            max,
+            last_damage: f64::MIN,
+        }
+    }
+
+    /// Whether a hit at `now` would deal damage, hits are ignored for a moment after the last one
+    pub fn vulnerable(&self, now: f64) -> bool {
+        now > self.last_damage + DAMAGE_COOLDOWN
+    }
+
+    /// Deals `damage` unless the entity was just hit
+    pub fn hit(&mut self, damage: f32, now: f64) -> bool {
+        if !self.vulnerable(now) {
+            return false;
+        }
+
+        self.last_damage = now;
+        self.current -= damage;
+        true
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Pixels per second
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Velocity(pub Vec2);
+
+/// Drawn as its rect filled with `color`, or as its sheet tinted with `color`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    pub color: Color,
+    pub sheet: Option<Sheet>,
+}
+
+/// Animation stretched over the rect, its frames side by side in the image. The image has to be
+/// loaded before it is drawn (see `GameScene::init`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sheet {
+    pub image_file: &'static str,
+    pub frames: u16,
+    /// Seconds each frame stays on screen
+    pub frame_duration: f32,
+}
+
+/// Side an entity fights for, projectiles only hit entities of another faction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Faction {
+    Player,
+    Enemy,
+}
+
+/// Deals damage to what it touches and goes away after `expires` or when it hits a wall
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Projectile {
+    pub damage: f32,
+    /// Targets it goes through before going away, 0 to stop at the first one
+    pub pierce: u8,
+    pub hits: u8,
+    /// Time it goes away at
+    pub expires: f64,
+}
+impl Projectile {
+    /// Counts a target hit, returns whether the projectile is done
+    pub fn hit(&mut self) -> bool {
+        self.hits += 1;
+        self.hits > self.pierce
+    }
+}
+
+/// Walks towards the player and does what its kind does once it noticed the player, and keeps its
+/// room locked until it dies
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Enemy {
+    pub kind: &'static EnemyKind,
+    /// Room (in `Manager::rects`) the enemy belongs to, the room is cleared once all of its enemies die
+    pub room: Option<usize>,
+    /// Time of the last shot or summon
+    pub last_attack: f64,
+    /// Enemy that called this one in, summoners only keep so many of their summons around
+    pub summoner: Option<Entity>,
+    pub state: AiState,
+    /// Time the enemy got into its state
+    pub since: f64,
+    /// Last time the enemy saw the player, or was told about it
+    pub last_seen: f64,
+    /// Where it spawned, it patrols around there
+    pub home: Vec2,
+}
+impl Enemy {
+    pub fn set_state(&mut self, state: AiState, now: f64) {
+        self.state = state;
+        self.since = now;
+    }
+
+    /// Whether the enemy knows about the player, rooms lock once one of their enemies does
+    pub fn aware(&self) -> bool {
+        !matches!(self.state, AiState::Idle | AiState::Patrol(_))
+    }
+}
+
+/// What an enemy is up to, moved between by `systems::think`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AiState {
+    /// Stands around until it sees the player, or gets bored and patrols
+    Idle,
+    /// Walks to a point around its home
+    Patrol(Vec2),
+    /// Just noticed the player, or was told by another enemy of its room, and takes a moment to
+    /// react
+    Alert,
+    /// Follows the player, for a while after losing sight of it
+    Chase,
+    /// In range and in sight of the player, doing what its kind does
+    Attack,
+    /// Too hurt to fight, runs away from the player until it lost it
+    Flee,
+    /// Just got hit, can't do anything for a moment
+    Stunned,
+}
+impl AiState {
+    /// Name it is saved under, patrols are saved without their point
+    pub fn name(&self) -> &'static str {
+        match self {
+            AiState::Idle => "idle",
+            AiState::Patrol(_) => "patrol",
+            AiState::Alert => "alert",
+            AiState::Chase => "chase",
+            AiState::Attack => "attack",
+            AiState::Flee => "flee",
+            AiState::Stunned => "stunned",
+        }
+    }
+
+    /// State saved as `name`, patrols head back `home`
+    pub fn from_name(name: &str, home: Vec2) -> Option<AiState> {
+        Some(match name {
+            "idle" => AiState::Idle,
+            "patrol" => AiState::Patrol(home),
+            "alert" => AiState::Alert,
+            "chase" => AiState::Chase,
+            "attack" => AiState::Attack,
+            "flee" => AiState::Flee,
+            "stunned" => AiState::Stunned,
+            _ => return None,
+        })
+    }
+}
+
+/// Entity being followed, projectiles with one home in on it. It may be despawned at any point,
+/// the target is dropped once it is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target(pub Entity);
+
+/// Stopped by walls and closed doors
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Collider;
@@ -0,0 +1,131 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use super::Entity;
+
+/// Hands out entity ids, reusing the indexes of freed ids with a new generation. Ids can be
+/// reserved through a shared reference, from any thread, while the entities are being read, they
+/// are alive once `flush` is called
+#[derive(Debug, Default)]
+pub struct Allocator {
+    /// Generation of the id at each index, and whether it is alive
+    slots: Vec<(u32, bool)>,
+    /// Indexes of freed ids
+    free: Vec<u32>,
+    /// Reservations count down from `free.len()`, the ones taken while it is negative get new
+    /// indexes after the `slots`
+    cursor: AtomicI64,
+}
+impl Allocator {
+    /// Id for an entity that is only alive after the next `flush`
+    pub fn reserve(&self) -> Entity {
+        let cursor = self.cursor.fetch_sub(1, Ordering::Relaxed);
+        if cursor > 0 {
+            let index = self.free[cursor as usize - 1];
+            Entity {
+                index,
+                generation: self.slots[index as usize].0,
+            }
+        } else {
+            Entity {
+                index: (self.slots.len() as i64 - cursor) as u32,
+                generation: 0,
+            }
+        }
+    }
+
+    /// Makes every reserved id alive
+    pub fn flush(&mut self) {
+        let cursor = *self.cursor.get_mut();
+        let reused = self.free.split_off(cursor.max(0) as usize);
+        for index in reused {
+            self.slots[index as usize].1 = true;
+        }
+        if cursor < 0 {
+            let added = self.slots.len() + (-cursor) as usize;
+            self.slots.resize(added, (0, true));
+        }
+        *self.cursor.get_mut() = self.free.len() as i64;
+    }
+
+    pub fn alloc(&mut self) -> Entity {
+        let entity = self.reserve();
+        self.flush();
+        entity
+    }
+
+    /// Frees the id for a new generation to reuse, returns false if it wasn't alive
+    pub fn free(&mut self, entity: Entity) -> bool {
+        self.flush();
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        let slot = &mut self.slots[entity.index as usize];
+        *slot = (slot.0.wrapping_add(1), false);
+        self.free.push(entity.index);
+        *self.cursor.get_mut() = self.free.len() as i64;
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.slots.get(entity.index as usize) == Some(&(entity.generation, true))
+    }
+
+    /// Every alive id, in index order
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, alive))| *alive)
+            .map(|(index, (generation, _))| Entity {
+                index: index as u32,
+                generation: *generation,
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[test]
+fn allocator() {
+    use std::collections::HashSet;
+
+    let mut ids = Allocator::default();
+    let first = ids.alloc();
+    let second = ids.alloc();
+    assert!(ids.free(first));
+    assert!(!ids.free(first));
+
+    // The freed index comes back with a new generation, and the old id stays dead
+    let reused = ids.alloc();
+    assert_eq!(reused.index, first.index);
+    assert_ne!(reused, first);
+    assert!(!ids.is_alive(first) && ids.is_alive(reused) && ids.is_alive(second));
+
+    // Reserving from several threads at once hands out different ids, alive once flushed
+    ids.free(second);
+    let reserved = std::thread::scope(|scope| {
+        let threads = (0..4)
+            .map(|_| scope.spawn(|| (0..50).map(|_| ids.reserve()).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        let reserved = threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap());
+        reserved.collect::<Vec<_>>()
+    });
+    assert_eq!(reserved.iter().collect::<HashSet<_>>().len(), 200);
+    assert!(reserved.contains(&Entity {
+        index: second.index,
+        generation: second.generation + 1
+    }));
+    assert!(reserved.iter().all(|entity| !ids.is_alive(*entity)));
+    ids.flush();
+    assert!(reserved.iter().all(|entity| ids.is_alive(*entity)));
+    assert_eq!(ids.len(), 201);
+}
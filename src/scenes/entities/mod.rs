@@ -0,0 +1,538 @@
+pub mod components;
+pub mod ids;
+pub mod systems;
+
+use self::components::{
+    AiState, Bundle, Collider, Enemy, Faction, Health, Projectile, Sprite, Target, Velocity,
+};
+use self::ids::Allocator;
+use super::context::{Command, Time, World};
+use super::objects::enemies::enemy;
+use super::objects::enemies::flow::FlowField;
+use super::objects::shapes::rect::Rect;
+use super::room_gen::rng::Rng;
+use super::save::EnemySave;
+
+/// Id of an entity. The index of a despawned entity is reused with a new generation, so an old id
+/// never refers to the entity spawned in its place. Entities hold on to each other through ids (see
+/// `Target`), which act as weak references: the components of a despawned entity are `None`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+/// Components of one kind, indexed by entity
+pub struct Storage<T> {
+    components: Vec<Option<(u32, T)>>,
+}
+impl<T> Default for Storage<T> {
+    fn default() -> Storage<T> {
+        Storage { components: vec![] }
+    }
+}
+impl<T> Storage<T> {
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        let index = entity.index as usize;
+        if index >= self.components.len() {
+            self.components.resize_with(index + 1, || None);
+        }
+        self.components[index] = Some((entity.generation, component));
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.components.get_mut(entity.index as usize)?;
+        match slot {
+            Some((generation, _)) if *generation == entity.generation => {
+                slot.take().map(|(_, component)| component)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.components.get(entity.index as usize)? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.components.get_mut(entity.index as usize)? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.components
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let (generation, component) = slot.as_ref()?;
+                let entity = Entity {
+                    index: index as u32,
+                    generation: *generation,
+                };
+                Some((entity, component))
+            })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.components
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let (generation, component) = slot.as_mut()?;
+                let entity = Entity {
+                    index: index as u32,
+                    generation: *generation,
+                };
+                Some((entity, component))
+            })
+    }
+}
+
+/// Every entity of the floor but the player, and their components. Nothing is spawned or despawned
+/// while the systems run, that is asked for with commands and only applied once the frame is over
+#[derive(Default)]
+pub struct Entities {
+    ids: Allocator,
+    /// Leads the enemies to the player
+    flow: FlowField,
+    /// Random choices of the systems, seeded by the floor so they play out the same every time
+    rng: Rng,
+
+    pub rects: Storage<Rect>,
+    pub healths: Storage<Health>,
+    pub velocities: Storage<Velocity>,
+    pub sprites: Storage<Sprite>,
+    pub factions: Storage<Faction>,
+    pub projectiles: Storage<Projectile>,
+    pub enemies: Storage<Enemy>,
+    pub targets: Storage<Target>,
+    pub colliders: Storage<Collider>,
+}
+impl Entities {
+    pub fn spawn(&mut self, bundle: Bundle) -> Entity {
+        let entity = self.ids.alloc();
+        self.insert(entity, bundle);
+        entity
+    }
+
+    /// Id for an entity spawned later on with `insert`, can be called while the entities are being
+    /// read (see `Context::spawn`)
+    pub fn reserve(&self) -> Entity {
+        self.ids.reserve()
+    }
+
+    /// Gives the components of `bundle` to a reserved or alive entity
+    pub fn insert(&mut self, entity: Entity, bundle: Bundle) {
+        self.ids.flush();
+        if !self.is_alive(entity) {
+            return;
+        }
+
+        macro_rules! insert {
+            ($($storage: ident: $component: ident),*) => {
+                $(if let Some(component) = bundle.$component {
+                    self.$storage.insert(entity, component);
+                })*
+            };
+        }
+        insert!(
+            rects: rect,
+            healths: health,
+            velocities: velocity,
+            sprites: sprite,
+            factions: faction,
+            projectiles: projectile,
+            enemies: enemy,
+            targets: target,
+            colliders: collider
+        );
+    }
+
+    /// Removes the entity and its components, returns false if it was already gone
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        self.ids.flush();
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        self.rects.remove(entity);
+        self.healths.remove(entity);
+        self.velocities.remove(entity);
+        self.sprites.remove(entity);
+        self.factions.remove(entity);
+        self.projectiles.remove(entity);
+        self.enemies.remove(entity);
+        self.targets.remove(entity);
+        self.colliders.remove(entity);
+        self.ids.free(entity)
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.ids.is_alive(entity)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.ids.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Despawns every entity, and forgets the floor they were on for the one generated from `seed`
+    pub fn clear(&mut self, seed: u64) {
+        for entity in self.iter().collect::<Vec<_>>() {
+            self.despawn(entity);
+        }
+        self.flow = FlowField::default();
+        self.rng = Rng::new(seed);
+    }
+
+    /// Snapshot of the enemies, to be put back with `load_enemies`
+    pub fn enemy_saves(&self) -> Vec<EnemySave> {
+        self.enemies
+            .iter()
+            .filter_map(|(entity, enemy)| {
+                let rect = self.rects.get(entity)?;
+                let health = self.healths.get(entity)?;
+                Some(EnemySave {
+                    kind: enemy.kind.name.to_owned(),
+                    position: rect.get_center().into(),
+                    health: health.current,
+                    max_health: health.max,
+                    state: enemy.state.name().to_owned(),
+                    home: enemy.home.into(),
+                    room: enemy.room,
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces every entity of the floor of `seed` with the saved enemies, loaded at `now`
+    pub fn load_enemies(&mut self, seed: u64, saves: &[EnemySave], now: f64) -> Result<(), String> {
+        self.clear(seed);
+        for saved in saves {
+            self.spawn(enemy::from_save(saved, now)?);
+        }
+        Ok(())
+    }
+
+    /// Runs every system once, what they ask for is pushed onto `commands`
+    pub fn update(
+        &mut self,
+        world: &World,
+        player: &Rect,
+        time: Time,
+        commands: &mut Vec<Command>,
+    ) {
+        systems::think(self, world, player, time.now);
+        systems::chase(self, world, player, time.delta);
+        systems::behave(self, player, time.now, commands);
+        systems::homing(self);
+        systems::movement(self, world, time.delta);
+        systems::expire(self, time.now, commands);
+        systems::collision(self, world, player, time.now, commands);
+    }
+
+    /// Applies the commands about entities and leaves the others in `commands`. Hits land first,
+    /// then the dead and despawned are removed and the spawned are added, so despawning twice does
+    /// nothing and spawned entities only update from the next frame. Returns the number of enemies
+    /// killed
+    pub fn apply(&mut self, commands: &mut Vec<Command>, now: f64) -> usize {
+        let mut despawned = vec![];
+        let mut spawned = vec![];
+        for command in std::mem::take(commands) {
+            match command {
+                Command::Spawn(entity, bundle) => spawned.push((entity, *bundle)),
+                Command::Despawn(entity) => despawned.push(entity),
+                Command::Hit { entity, damage } => {
+                    let hurt = (self.healths.get_mut(entity))
+                        .is_some_and(|health| health.hit(damage, now));
+                    // Hit enemies are stunned for a moment, and know where the shot came from
+                    if let Some(enemy) = self.enemies.get_mut(entity).filter(|_| hurt) {
+                        enemy.set_state(AiState::Stunned, now);
+                        enemy.last_seen = now;
+                    }
+                }
+                command => commands.push(command),
+            }
+        }
+
+        let dead = self
+            .healths
+            .iter()
+            .filter(|(_, health)| health.is_dead())
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        let kills = dead
+            .iter()
+            .filter(|entity| self.enemies.contains(**entity))
+            .count();
+        for entity in dead.into_iter().chain(despawned) {
+            self.despawn(entity);
+        }
+        for (entity, bundle) in spawned {
+            self.insert(entity, bundle);
+        }
+        kills
+    }
+
+    pub fn draw(&self, now: f64) {
+        systems::draw(self, now);
+    }
+}
+
+/// What the entity tests run in: the first floor of a small dungeon, with the player a few tiles
+/// to the left of its spawn, in the same room
+#[cfg(test)]
+pub struct Fixture {
+    pub world: World,
+    /// Player spawn of the floor
+    pub spawn: macroquad::prelude::Vec2,
+    pub player: Rect,
+}
+#[cfg(test)]
+impl Fixture {
+    pub fn new() -> Fixture {
+        use super::dungeon_manager::Manager;
+        use super::room_gen::config::SMALL;
+        use crate::util::SQUARE_SIZE;
+
+        let world = World::new(Manager::new(5, SMALL).unwrap());
+        let spawn = world.floor().player_position();
+        let left = macroquad::prelude::vec2(3.0 * SQUARE_SIZE, 0.0);
+        Fixture {
+            world,
+            spawn,
+            player: Rect::new_center_vec(spawn - left, 30.0, 30.0),
+        }
+    }
+
+    /// Player bullet that lives for a second
+    pub fn bullet() -> super::objects::bullet::BulletConfig {
+        super::objects::bullet::BulletConfig {
+            speed: 100.0,
+            max_lifespan: 1.0,
+            spread: 0.0,
+            bullet_size: 10.0,
+            pierce: 0,
+            damage: 4.0,
+            friendly: true,
+        }
+    }
+
+    pub fn kind(name: &str) -> &'static super::objects::enemies::kinds::EnemyKind {
+        super::objects::enemies::kinds::enemy_kind(name).unwrap()
+    }
+
+    /// Runs the frames from `from` to `to` seconds with the player at `player`, applying the
+    /// commands about entities. Returns the damage dealt to the player
+    pub fn run(&self, entities: &mut Entities, from: f64, to: f64, player: &Rect) -> Vec<f32> {
+        let mut time = Time {
+            now: from,
+            delta: 0.05,
+        };
+        let mut damage = vec![];
+        while time.now < to {
+            let mut commands = vec![];
+            entities.update(&self.world, player, time, &mut commands);
+            entities.apply(&mut commands, time.now);
+            damage.extend(commands.iter().filter_map(|command| match command {
+                Command::HitPlayer(damage) => Some(*damage),
+                _ => None,
+            }));
+            time.now += time.delta as f64;
+        }
+        damage
+    }
+}
+
+#[cfg(test)]
+impl Default for Fixture {
+    fn default() -> Fixture {
+        Fixture::new()
+    }
+}
+
+#[test]
+fn bullet_kills_enemy() {
+    use macroquad::prelude::vec2;
+
+    use super::objects::bullet::bullet;
+    use super::objects::enemies::enemy::enemy;
+    use crate::util::project;
+
+    let Fixture {
+        world,
+        spawn,
+        player,
+    } = Fixture::new();
+    let config = Fixture::bullet();
+    let mut entities = Entities::default();
+    // The first bullet is on top of an enemy that dies in one hit, the others don't touch anything
+    let bullets =
+        [0.0, -25.0, 25.0].map(|y| entities.spawn(bullet(0.0, spawn + vec2(0.0, y), config, 0.0)));
+    // Chasing exploders, that only blow up when right next to the player
+    let kind = Fixture::kind("Exploder");
+    let enemies = [
+        Bundle {
+            health: Some(Health::new(1.0)),
+            ..enemy(kind, spawn + vec2(10.0, 0.0), None)
+        },
+        enemy(kind, spawn - vec2(0.0, 100.0), None),
+        enemy(kind, spawn + vec2(0.0, 100.0), None),
+    ]
+    .map(|enemy| entities.spawn(enemy));
+    for enemy in &enemies {
+        let enemy = entities.enemies.get_mut(*enemy).unwrap();
+        enemy.set_state(AiState::Chase, 0.5);
+        enemy.last_seen = 0.5;
+    }
+    let positions = |entities: &Entities| {
+        let all = bullets.iter().chain(&enemies);
+        all.map(|entity| entities.rects.get(*entity).unwrap().get_center())
+            .collect::<Vec<_>>()
+    };
+    let before = positions(&entities);
+
+    // The enemies walk away from the bullets, to the player
+    let time = Time {
+        now: 0.5,
+        delta: 0.1,
+    };
+    let spawned = entities.reserve();
+    let mut commands = vec![
+        Command::Spawn(spawned, Box::new(bullet(0.0, spawn, config, 0.0))),
+        Command::HitPlayer(1.0),
+    ];
+    entities.update(&world, &player, time, &mut commands);
+
+    // Everything moved exactly once, and nothing got spawned, despawned or hit while updating
+    assert_eq!(entities.len(), 6);
+    assert!(!entities.is_alive(spawned));
+    assert_eq!(entities.healths.get(enemies[0]).unwrap().current, 1.0);
+    let after = positions(&entities);
+    for (before, after) in before[..3].iter().zip(&after[..3]) {
+        let expected = project(*before, 0.0, config.speed * time.delta);
+        assert!(after.distance(expected) < 0.001);
+    }
+    for (before, after) in before[3..].iter().zip(&after[3..]) {
+        let step = before.distance(*after);
+        assert!(step > 0.0 && step <= kind.speed * time.delta + 0.01);
+    }
+
+    // The first bullet and the enemy under it are gone, once, and the spawned bullet is there
+    let kills = entities.apply(&mut commands, time.now);
+    assert_eq!(kills, 1);
+    assert!(matches!(commands[..], [Command::HitPlayer(_)]));
+    assert_eq!(entities.len(), 5);
+    assert!(entities.rects.contains(spawned));
+    assert!(!entities.is_alive(bullets[0]) && !entities.is_alive(enemies[0]));
+    assert!(bullets[1..].iter().all(|bullet| entities.is_alive(*bullet)));
+    assert!(enemies[1..].iter().all(|enemy| entities.is_alive(*enemy)));
+
+    // Despawning or hitting again does nothing, even once the index is reused
+    let reused = entities.spawn(enemy(kind, spawn, None));
+    commands.clear();
+    commands.push(Command::Despawn(bullets[0]));
+    commands.push(Command::Hit {
+        entity: enemies[0],
+        damage: 20.0,
+    });
+    assert_eq!(entities.apply(&mut commands, time.now + 1.0), 0);
+    assert_eq!(entities.len(), 6);
+    assert_eq!(
+        entities.healths.get(reused).unwrap().current,
+        kind.max_health
+    );
+
+    // A hit of exactly its max health kills it
+    commands.push(Command::Hit {
+        entity: reused,
+        damage: kind.max_health,
+    });
+    assert_eq!(entities.apply(&mut commands, time.now + 2.0), 1);
+    assert!(!entities.is_alive(reused));
+}
+
+#[test]
+fn targets() {
+    use macroquad::prelude::{vec2, Vec2};
+
+    use super::objects::bullet::bullet;
+    use super::objects::enemies::enemy::enemy;
+    use super::objects::enemies::kinds::ENEMIES;
+    use crate::util::angle;
+
+    let config = Fixture::bullet();
+    let mut entities = Entities::default();
+    let target = entities.spawn(enemy(&ENEMIES[0], vec2(0.0, 100.0), None));
+    let homing = entities.spawn(Bundle {
+        target: Some(Target(target)),
+        ..bullet(0.0, Vec2::ZERO, config, 0.0)
+    });
+    let direction = |entities: &Entities| {
+        let velocity = entities.velocities.get(homing).unwrap().0;
+        angle(Vec2::ZERO, velocity)
+    };
+
+    // Turns towards the target at the same speed
+    systems::homing(&mut entities);
+    assert!((direction(&entities) - angle(Vec2::ZERO, vec2(0.0, 100.0))).abs() < 0.001);
+    assert!((entities.velocities.get(homing).unwrap().0.length() - 100.0).abs() < 0.001);
+
+    // Once the target is gone the bullet doesn't follow whatever takes its index
+    entities.despawn(target);
+    let other = entities.spawn(enemy(&ENEMIES[0], vec2(100.0, 0.0), None));
+    assert_eq!(other.index, target.index);
+    let before = direction(&entities);
+    systems::homing(&mut entities);
+    assert_eq!(direction(&entities), before);
+    assert!(!entities.targets.contains(homing));
+}
+
+#[test]
+fn load_replaces_the_floor_enemies() {
+    use crate::util::tile_center;
+
+    let fixture = Fixture::new();
+    let floor = fixture.world.floor();
+    // What `GameScene::load_floor` spawns, before a save is loaded over it
+    let generate = |entities: &mut Entities| {
+        entities.clear(floor.seed);
+        for (room, spawns) in floor.enemy_spawns.iter().enumerate() {
+            for spawn in spawns {
+                let kind = Fixture::kind("Charger");
+                entities.spawn(enemy::enemy(kind, tile_center(*spawn), Some(room)));
+            }
+        }
+    };
+
+    let mut entities = Entities::default();
+    generate(&mut entities);
+    let killed = entities.iter().next().unwrap();
+    entities.despawn(killed);
+    let saves = entities.enemy_saves();
+    assert!(!saves.is_empty());
+
+    let mut loaded = Entities::default();
+    generate(&mut loaded);
+    loaded.load_enemies(floor.seed, &saves, 0.0).unwrap();
+    assert_eq!(loaded.len(), saves.len());
+    // Despawned ids are reused, in another order
+    let reloaded = loaded.enemy_saves();
+    assert!(saves.iter().all(|saved| reloaded.contains(saved)));
+}
@@ -0,0 +1,754 @@
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::TAU;
+
+use macroquad::prelude::{
+    draw_text, draw_texture_ex, vec2, DrawTextureParams, Rect as SourceRect, Vec2, YELLOW,
+};
+
+use super::components::{AiState, Faction, Velocity};
+use super::{Entities, Entity};
+use crate::camera::ShakeConfig;
+use crate::scenes::context::{Command, World};
+use crate::scenes::dungeon_manager::Floor;
+use crate::scenes::objects::assets::get_image;
+use crate::scenes::objects::bullet::bullet;
+use crate::scenes::objects::enemies::astar::{pos_valid, HashVec2};
+use crate::scenes::objects::enemies::enemy::enemy as enemy_bundle;
+use crate::scenes::objects::enemies::kinds::Behaviour;
+use crate::scenes::objects::shapes::rect::Rect;
+use crate::scenes::room_gen::rng::Rng;
+use crate::unwrap_or_continue;
+use crate::util::{angle, project, SQUARE_SIZE};
+
+const EXPLOSION_SHAKE: ShakeConfig = ShakeConfig {
+    duration: 0.4,
+    intensity: 120.0,
+};
+
+/// Seconds an idle enemy stands around before patrolling
+const IDLE_TIME: f64 = 2.0;
+/// Seconds a patrol lasts at most, for points the enemy can't walk straight to
+const PATROL_TIME: f64 = 4.0;
+/// Tiles around its home an enemy patrols to
+const PATROL_RANGE: i32 = 3;
+/// Seconds an enemy takes to react once it noticed the player
+const ALERT_TIME: f64 = 0.5;
+/// Seconds a chasing or fleeing enemy keeps going after losing sight of the player
+const LOSE_TIME: f64 = 3.0;
+/// Seconds an enemy can't do anything for after getting hit
+const STUN_TIME: f64 = 0.3;
+
+/// Distance to the player chargers stop charging at
+const CHARGE_STOP: f32 = SQUARE_SIZE * 2.0;
+
+/// Enemies closer than this push each other apart
+const SEPARATION_RANGE: f32 = SQUARE_SIZE;
+/// How hard enemies push each other apart when on top of each other, against a pull of 1 towards
+/// the player
+const SEPARATION: f32 = 6.0;
+
+/// Moves the enemies from state to state (see `AiState`). Enemies see the player within the view
+/// distance of their kind when nothing blocks their line of sight, and one of them noticing the
+/// player alerts the rest of its room. Enemies see all around them, only distance and walls hide
+/// the player from them, and the ones chasing it lose it once out of sight long enough
+pub fn think(entities: &mut Entities, world: &World, player: &Rect, now: f64) {
+    let Entities {
+        enemies,
+        rects,
+        healths,
+        rng,
+        ..
+    } = entities;
+    let floor = world.floor();
+    let target = player.get_center();
+
+    let mut sight = HashMap::<Entity, bool>::new();
+    let mut woken = HashSet::new();
+    for (entity, enemy) in enemies.iter() {
+        let center = unwrap_or_continue!(rects.get(entity)).get_center();
+        let sees =
+            center.distance(target) <= enemy.kind.view && world.line_of_sight(center, target);
+        if let Some(room) = enemy.room.filter(|_| sees) {
+            woken.insert(room);
+        }
+        sight.insert(entity, sees);
+    }
+
+    for (entity, enemy) in enemies.iter_mut() {
+        let center = unwrap_or_continue!(rects.get(entity)).get_center();
+        let sees = sight[&entity];
+        if sees {
+            enemy.last_seen = now;
+        }
+        let elapsed = now - enemy.since;
+        let in_range = sees && center.distance(target) < enemy.kind.behaviour.range();
+        let lost = now - enemy.last_seen > LOSE_TIME;
+        let hurt = healths
+            .get(entity)
+            .is_some_and(|health| health.current < health.max * enemy.kind.flee_health);
+        let woken = enemy.room.is_some_and(|room| woken.contains(&room));
+
+        let state = match enemy.state {
+            AiState::Idle | AiState::Patrol(_) if sees || woken => AiState::Alert,
+            AiState::Idle if elapsed > IDLE_TIME => {
+                AiState::Patrol(patrol_point(floor, enemy.home, rng))
+            }
+            AiState::Patrol(point)
+                if elapsed > PATROL_TIME || center.distance(point) < SQUARE_SIZE / 4.0 =>
+            {
+                AiState::Idle
+            }
+            AiState::Alert if elapsed > ALERT_TIME && in_range => AiState::Attack,
+            AiState::Alert if elapsed > ALERT_TIME => AiState::Chase,
+            AiState::Chase | AiState::Attack if hurt => AiState::Flee,
+            AiState::Chase | AiState::Attack | AiState::Flee if lost => AiState::Idle,
+            AiState::Chase if in_range => AiState::Attack,
+            AiState::Attack if !in_range => AiState::Chase,
+            AiState::Stunned if elapsed > STUN_TIME => AiState::Chase,
+            _ => continue,
+        };
+        if state == AiState::Alert {
+            enemy.last_seen = now;
+        }
+        enemy.set_state(state, now);
+    }
+}
+
+/// Center of a random walkable tile of the room around `home`, `home` itself when the tile picked
+/// isn't one
+fn patrol_point(floor: &Floor, home: Vec2, rng: &mut Rng) -> Vec2 {
+    let tile = HashVec2::from_world(home);
+    let mut offset = || rng.gen_range(-PATROL_RANGE, PATROL_RANGE + 1);
+    let point = HashVec2::new(tile.x + offset(), tile.y + offset());
+    let same_room = |tile: HashVec2| floor.room_index(tile.x as usize, tile.y as usize);
+    if pos_valid(&point, &floor.room) && same_room(point) == same_room(tile) {
+        point.to_world()
+    } else {
+        home
+    }
+}
+
+/// Enemies walk depending on their state: chasing and attacking ones follow the flow field to
+/// the player's tile and walk up to the player once on its tile, fleeing ones go up the field,
+/// patrolling ones walk straight to their point and the others stand still. The field is computed
+/// again whenever the player changes tile, through the player's room and the corridors (every room
+/// while the player is in a corridor), the enemies it doesn't reach stand still. Enemies push the
+/// ones around them away so they don't pile up on the same tile
+pub fn chase(entities: &mut Entities, world: &World, player: &Rect, delta: f32) {
+    let Entities {
+        enemies,
+        rects,
+        velocities,
+        flow,
+        ..
+    } = entities;
+    let floor = world.floor();
+    let goal = HashVec2::from_world(player.get_center());
+    if flow.goal() != Some(goal) {
+        let room = floor
+            .room_index_at(player.get_center())
+            .map(|index| floor.rects[index]);
+        flow.compute(goal, &floor.room, |tile| {
+            let (x, y) = (tile.x as usize, tile.y as usize);
+            match room {
+                Some(room) => {
+                    room.touches_point(&vec2(x as f32, y as f32))
+                        || floor.room_index(x, y).is_none()
+                }
+                None => true,
+            }
+        });
+    }
+
+    // Enemies by tile, the ones close enough to push each other are at most a tile apart
+    let mut crowd = HashMap::<HashVec2, Vec<(Entity, Vec2)>>::new();
+    for (entity, _) in enemies.iter() {
+        let center = unwrap_or_continue!(rects.get(entity)).get_center();
+        crowd
+            .entry(HashVec2::from_world(center))
+            .or_default()
+            .push((entity, center));
+    }
+
+    for (entity, enemy) in enemies.iter() {
+        let center = unwrap_or_continue!(rects.get(entity)).get_center();
+        let speed = enemy.kind.speed * floor.tile_at(center).properties().speed;
+        let tile = HashVec2::from_world(center);
+
+        // Heading to the next tile, or stopping next to the player once on its tile
+        let (target, stop) = match enemy.state {
+            AiState::Chase | AiState::Attack => match flow.next(tile, &floor.room) {
+                Some(next) => (Some(next.to_world()), 0.0),
+                None if tile == goal => (Some(player.get_center()), SQUARE_SIZE),
+                None => (None, 0.0),
+            },
+            AiState::Flee => (
+                flow.away(tile, &floor.room).map(|away| away.to_world()),
+                0.0,
+            ),
+            AiState::Patrol(point) => (Some(point), 0.0),
+            AiState::Idle | AiState::Alert | AiState::Stunned => (None, 0.0),
+        };
+        // Slowing down on the target instead of going past it
+        let pull = target.map_or(Vec2::ZERO, |target| {
+            let distance = (center.distance(target) - stop).max(0.0);
+            (target - center).normalize_or_zero() * (distance / (speed * delta)).min(1.0)
+        });
+
+        let mut push = Vec2::ZERO;
+        for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+            let others = crowd.get(&HashVec2::new(tile.x + dx, tile.y + dy));
+            for (other, other_center) in others.into_iter().flatten() {
+                let away = center - *other_center;
+                let distance = away.length();
+                if *other == entity || distance >= SEPARATION_RANGE {
+                    continue;
+                }
+                // Enemies right on top of each other split up along their ids
+                let away = match away.normalize_or_zero() {
+                    Vec2::ZERO => project(Vec2::ZERO, entity.index as f32, 1.0),
+                    away => away,
+                };
+                push += away * SEPARATION * (1.0 - distance / SEPARATION_RANGE);
+            }
+        }
+
+        let direction = (pull + push).clamp_length_max(1.0);
+        velocities.insert(entity, Velocity(direction * speed));
+    }
+}
+
+/// Enemies hurt the player by touching it, and do what their kind does while attacking, once
+/// `chase` picked where they walk (see `Behaviour`)
+pub fn behave(entities: &mut Entities, player: &Rect, now: f64, commands: &mut Vec<Command>) {
+    let Entities {
+        ids,
+        enemies,
+        rects,
+        velocities,
+        ..
+    } = entities;
+    let mut summons = HashMap::<Entity, usize>::new();
+    for (_, enemy) in enemies.iter() {
+        if let Some(summoner) = enemy.summoner {
+            *summons.entry(summoner).or_default() += 1;
+        }
+    }
+
+    let target = player.get_center();
+    for (entity, enemy) in enemies.iter_mut() {
+        let rect = unwrap_or_continue!(rects.get(entity));
+        let center = rect.get_center();
+        let distance = center.distance(target);
+        if enemy.kind.contact_damage > 0.0 && rect.touches_rect(player) {
+            commands.push(Command::HitPlayer(enemy.kind.contact_damage));
+        }
+        if enemy.state != AiState::Attack {
+            continue;
+        }
+        let last_attack = enemy.last_attack;
+        let ready = |delay: f32| now > last_attack + delay as f64;
+
+        match enemy.kind.behaviour {
+            Behaviour::Charger { range, charge } => {
+                // Back to walking once it got to the player, to stay in the crowd around it
+                let charging = distance < range && distance > CHARGE_STOP;
+                if let Some(velocity) = velocities.get_mut(entity).filter(|_| charging) {
+                    velocity.0 *= charge;
+                }
+            }
+            Behaviour::Shooter {
+                fire_delay,
+                bullet: config,
+                ..
+            } => {
+                velocities.insert(entity, Velocity::default());
+                if ready(fire_delay) {
+                    enemy.last_attack = now;
+                    let bundle = bullet(angle(center, target), center, config, now);
+                    commands.push(Command::Spawn(ids.reserve(), Box::new(bundle)));
+                }
+            }
+            Behaviour::Exploder { damage, .. } => {
+                commands.push(Command::HitPlayer(damage));
+                commands.push(Command::Shake(EXPLOSION_SHAKE));
+                commands.push(Command::Despawn(entity));
+            }
+            Behaviour::Summoner {
+                delay, max, summon, ..
+            } => {
+                velocities.insert(entity, Velocity::default());
+                if ready(delay) && summons.get(&entity).copied().unwrap_or(0) < max {
+                    enemy.last_attack = now;
+                    let mut bundle = enemy_bundle(summon, center, enemy.room);
+                    // Called in to fight, they know where the player is
+                    if let Some(summoned) = &mut bundle.enemy {
+                        summoned.summoner = Some(entity);
+                        summoned.set_state(AiState::Chase, now);
+                        summoned.last_seen = now;
+                    }
+                    commands.push(Command::Spawn(ids.reserve(), Box::new(bundle)));
+                }
+            }
+            Behaviour::Turret {
+                fire_delay,
+                shots,
+                bullet: config,
+                ..
+            } => {
+                velocities.insert(entity, Velocity::default());
+                if ready(fire_delay) {
+                    enemy.last_attack = now;
+                    for shot in 0..shots {
+                        let direction = angle(center, target) + TAU * shot as f32 / shots as f32;
+                        let bundle = bullet(direction, center, config, now);
+                        commands.push(Command::Spawn(ids.reserve(), Box::new(bundle)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Projectiles with a target keep their speed but turn towards it
+pub fn homing(entities: &mut Entities) {
+    let Entities {
+        targets,
+        rects,
+        velocities,
+        projectiles,
+        ..
+    } = entities;
+    let mut lost = vec![];
+    for (entity, target) in targets.iter() {
+        if !projectiles.contains(entity) {
+            continue;
+        }
+        let center = unwrap_or_continue!(rects.get(entity)).get_center();
+        let velocity = unwrap_or_continue!(velocities.get_mut(entity));
+        match rects.get(target.0) {
+            Some(target) => {
+                let direction = angle(center, target.get_center());
+                velocity.0 = project(Vec2::ZERO, direction, velocity.0.length());
+            }
+            None => lost.push(entity),
+        }
+    }
+    for entity in lost {
+        targets.remove(entity);
+    }
+}
+
+/// Walls that only overlap a collider by this much on the other axis push it around their corner
+/// instead of stopping it, for entities not perfectly lined up with a corridor
+const CORNER_SLIP: f32 = SQUARE_SIZE / 6.0;
+
+/// Moves along the velocity, entities with a `Collider` one axis at a time, like the player, to
+/// slide along the walls they run into
+pub fn movement(entities: &mut Entities, world: &World, delta: f32) {
+    let Entities {
+        velocities,
+        rects,
+        colliders,
+        ..
+    } = entities;
+    for (entity, velocity) in velocities.iter() {
+        let rect = unwrap_or_continue!(rects.get_mut(entity));
+        let step = velocity.0 * delta;
+        if !colliders.contains(entity) {
+            rect.pos += step;
+            continue;
+        }
+
+        rect.pos.x += step.x;
+        for wall in world.walls_touching(rect) {
+            if !rect.touches_rect(&wall) {
+                continue;
+            }
+            let overlap = rect.get_bottom().min(wall.get_bottom()) - rect.pos.y.max(wall.pos.y);
+            if overlap <= CORNER_SLIP {
+                if rect.get_center().y < wall.get_center().y {
+                    rect.set_bottom(wall.get_top());
+                } else {
+                    rect.set_top(wall.get_bottom());
+                }
+            } else if step.x > 0.0 {
+                rect.set_right(wall.get_left());
+            } else {
+                rect.set_left(wall.get_right());
+            }
+        }
+
+        rect.pos.y += step.y;
+        for wall in world.walls_touching(rect) {
+            if !rect.touches_rect(&wall) {
+                continue;
+            }
+            let overlap = rect.get_right().min(wall.get_right()) - rect.pos.x.max(wall.pos.x);
+            if overlap <= CORNER_SLIP {
+                if rect.get_center().x < wall.get_center().x {
+                    rect.set_right(wall.get_left());
+                } else {
+                    rect.set_left(wall.get_right());
+                }
+            } else if step.y > 0.0 {
+                rect.set_bottom(wall.get_top());
+            } else {
+                rect.set_top(wall.get_bottom());
+            }
+        }
+    }
+}
+
+/// Projectiles go away once they expire
+pub fn expire(entities: &Entities, now: f64, commands: &mut Vec<Command>) {
+    for (entity, projectile) in entities.projectiles.iter() {
+        if now > projectile.expires {
+            commands.push(Command::Despawn(entity));
+        }
+    }
+}
+
+/// Projectiles break on walls and closed doors (breaking the destructible ones) and hit what they
+/// touch with health and another faction, the player being of `Faction::Player`
+pub fn collision(
+    entities: &mut Entities,
+    world: &World,
+    player: &Rect,
+    now: f64,
+    commands: &mut Vec<Command>,
+) {
+    let floor = world.floor();
+    let Entities {
+        projectiles,
+        rects,
+        healths,
+        factions,
+        ..
+    } = entities;
+    'projectiles: for (entity, projectile) in projectiles.iter_mut() {
+        let rect = unwrap_or_continue!(rects.get(entity));
+        let faction = factions.get(entity).copied();
+
+        for (x, y) in floor.tiles_touching(rect) {
+            let tile = floor.room[y][x].properties();
+            if tile.blocks_bullets {
+                if tile.destructible {
+                    commands.push(Command::BreakTile(x, y));
+                }
+                commands.push(Command::Despawn(entity));
+                continue 'projectiles;
+            }
+        }
+        if world.closed_doors().any(|wall| rect.touches_rect(wall)) {
+            commands.push(Command::Despawn(entity));
+            continue;
+        }
+
+        for (target, health) in healths.iter() {
+            let target_rect = unwrap_or_continue!(rects.get(target));
+            if target == entity
+                || factions.get(target).copied() == faction
+                || !rect.touches_rect(target_rect)
+                || !health.vulnerable(now)
+            {
+                continue;
+            }
+
+            commands.push(Command::Hit {
+                entity: target,
+                damage: projectile.damage,
+            });
+            if projectile.hit() {
+                commands.push(Command::Despawn(entity));
+                continue 'projectiles;
+            }
+        }
+
+        if faction != Some(Faction::Player) && rect.touches_rect(player) {
+            commands.push(Command::HitPlayer(projectile.damage));
+            if projectile.hit() {
+                commands.push(Command::Despawn(entity));
+            }
+        }
+    }
+}
+
+/// Sprites, with their sheet on the frame it is at `now`
+pub fn draw(entities: &Entities, now: f64) {
+    for (entity, sprite) in entities.sprites.iter() {
+        let rect = unwrap_or_continue!(entities.rects.get(entity));
+        let sheet = match sprite.sheet {
+            Some(sheet) => sheet,
+            None => {
+                rect.draw(sprite.color);
+                continue;
+            }
+        };
+
+        let texture = get_image(sheet.image_file);
+        let width = texture.width() / sheet.frames as f32;
+        let frame = (now / sheet.frame_duration as f64) as u64 % sheet.frames as u64;
+        draw_texture_ex(
+            texture,
+            rect.pos.x,
+            rect.pos.y,
+            sprite.color,
+            DrawTextureParams {
+                source: Some(SourceRect::new(
+                    frame as f32 * width,
+                    0.0,
+                    width,
+                    texture.height(),
+                )),
+                dest_size: Some(vec2(rect.width, rect.height)),
+                ..Default::default()
+            },
+        );
+    }
+
+    // Enemies that just noticed the player show it, so sneaking players know they were seen
+    for (entity, enemy) in entities.enemies.iter() {
+        let rect = unwrap_or_continue!(entities.rects.get(entity));
+        if enemy.state == AiState::Alert {
+            let x = rect.get_center().x - 4.0;
+            draw_text("!", x, rect.get_top() - 4.0, 24.0, YELLOW);
+        }
+    }
+}
+
+#[test]
+fn chase_around_walls() {
+    use super::Fixture;
+    use crate::scenes::context::Time;
+    use crate::scenes::objects::enemies::enemy::enemy;
+    use crate::util::tile_center;
+
+    let charger = Fixture::kind("Charger");
+    // The player stands in the room with the most spawns, with enemies everywhere else in it
+    let world = Fixture::new().world;
+    let floor = world.floor();
+    let (room, spawns) = (floor.enemy_spawns.iter().enumerate())
+        .max_by_key(|(_, spawns)| spawns.len())
+        .unwrap();
+    let player = Rect::new_center_vec(tile_center(spawns[0]), SQUARE_SIZE, SQUARE_SIZE);
+    // Enemies of other rooms are left out of the field, and don't see the player anyway
+    let (other, _) = (floor.enemy_spawns.iter().enumerate())
+        .find(|(other, spawns)| *other != room && !spawns.is_empty())
+        .unwrap();
+    let away = tile_center(floor.enemy_spawns[other][0]);
+    let spawn = |entities: &mut Entities| {
+        entities.clear(floor.seed);
+        let enemies = spawns[1..]
+            .iter()
+            .map(|spawn| entities.spawn(enemy(charger, tile_center(*spawn), None)))
+            .collect::<Vec<_>>();
+        // Already after the player
+        for enemy in &enemies {
+            let enemy = entities.enemies.get_mut(*enemy).unwrap();
+            enemy.set_state(AiState::Alert, 0.0);
+            enemy.last_seen = 0.0;
+        }
+        let idle = entities.spawn(enemy(charger, away, None));
+        (enemies, idle)
+    };
+    let mut entities = Entities::default();
+    let (enemies, idle) = spawn(&mut entities);
+    assert!(enemies.len() > 5);
+    let center =
+        |entities: &Entities, enemy: Entity| entities.rects.get(enemy).unwrap().get_center();
+
+    let play = |entities: &mut Entities| {
+        let mut time = Time {
+            now: 0.0,
+            delta: 0.05,
+        };
+        let mut commands = vec![];
+        for frame in 0..600 {
+            // Moving around inside its tile doesn't change the field
+            let mut moved = player;
+            moved.pos += vec2((frame % 3) as f32, 0.0);
+            entities.update(&world, &moved, time, &mut commands);
+            assert_eq!(
+                entities.flow.goal(),
+                Some(HashVec2::from_world(player.get_center()))
+            );
+
+            for enemy in enemies.iter().chain([&idle]) {
+                let rect = entities.rects.get(*enemy).unwrap();
+                assert!(!world.walls.iter().any(|wall| rect.touches_rect(wall)));
+            }
+            assert!(!entities.enemies.get(idle).unwrap().aware());
+            time.now += time.delta as f64;
+        }
+    };
+    play(&mut entities);
+
+    // Gathered around the player without piling up
+    for &enemy in &enemies {
+        let distance = center(&entities, enemy).distance(player.get_center());
+        assert!(distance < 5.0 * SQUARE_SIZE, "{}", distance);
+        for &other in &enemies {
+            let apart = center(&entities, enemy).distance(center(&entities, other));
+            assert!(enemy == other || apart > SQUARE_SIZE / 2.0, "{}", apart);
+        }
+    }
+    assert_eq!(floor.room_index_at(center(&entities, idle)), Some(other));
+
+    // Patrols are seeded by the floor, so the same frames play out exactly the same
+    let mut replay = Entities::default();
+    spawn(&mut replay);
+    play(&mut replay);
+    for entity in entities.iter() {
+        assert_eq!(center(&entities, entity), center(&replay, entity));
+        assert_eq!(
+            entities.enemies.get(entity).unwrap().state,
+            replay.enemies.get(entity).unwrap().state
+        );
+    }
+}
+
+#[test]
+fn behaviours() {
+    use super::Fixture;
+    use crate::scenes::objects::enemies::enemy::enemy;
+    use crate::scenes::objects::enemies::kinds::EnemyKind;
+
+    let fixture = Fixture::new();
+    let Fixture { spawn, player, .. } = fixture;
+    let kind = Fixture::kind;
+    let hits = |commands: &[Command]| {
+        let hits = commands.iter().filter_map(|command| match command {
+            Command::HitPlayer(damage) => Some(*damage),
+            _ => None,
+        });
+        hits.collect::<Vec<_>>()
+    };
+    let run = |entities: &mut Entities, from, to| fixture.run(entities, from, to, &player);
+
+    let attack = |entities: &mut Entities, enemy: Entity| {
+        entities.enemies.get_mut(enemy).unwrap().state = AiState::Attack;
+    };
+
+    // Shooters stop to shoot once they noticed the player, and their bullets hurt the player
+    let mut entities = Entities::default();
+    let shooter = entities.spawn(enemy(kind("Shooter"), spawn, None));
+    assert_eq!(run(&mut entities, 0.0, 1.0), vec![10.0]);
+    assert_eq!(entities.rects.get(shooter).unwrap().get_center(), spawn);
+
+    // Turrets shoot all around them
+    let mut entities = Entities::default();
+    let turret = entities.spawn(enemy(kind("Turret"), spawn, None));
+    attack(&mut entities, turret);
+    let mut commands = vec![];
+    behave(&mut entities, &player, 0.0, &mut commands);
+    entities.apply(&mut commands, 0.0);
+    assert_eq!(entities.projectiles.iter().count(), 8);
+    assert!(entities.factions.iter().all(|(_, f)| *f == Faction::Enemy));
+    assert_eq!(entities.velocities.get(turret), Some(&Velocity::default()));
+
+    // Exploders blow up next to the player, chargers hurt it by touching it even when not attacking
+    let mut entities = Entities::default();
+    let next_to = player.get_center() + vec2(40.0, 0.0);
+    let exploder = entities.spawn(enemy(kind("Exploder"), next_to, None));
+    let charger = entities.spawn(enemy(kind("Charger"), player.get_center(), None));
+    attack(&mut entities, exploder);
+    let mut commands = vec![];
+    behave(&mut entities, &player, 0.0, &mut commands);
+    assert_eq!(hits(&commands), vec![25.0, 10.0]);
+    entities.apply(&mut commands, 0.0);
+    assert!(!entities.is_alive(exploder) && entities.is_alive(charger));
+
+    // Summoners keep at most a few summons around, and call in more once they die
+    let mut entities = Entities::default();
+    let summoner = entities.spawn(enemy(kind("Summoner"), spawn, None));
+    let summons = |entities: &Entities| {
+        let summons = entities.enemies.iter();
+        summons
+            .filter(|(_, enemy)| enemy.summoner == Some(summoner))
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>()
+    };
+    run(&mut entities, 0.0, 30.0);
+    let (max, summon) = match kind("Summoner").behaviour {
+        Behaviour::Summoner { max, summon, .. } => (max, summon),
+        _ => unreachable!(),
+    };
+    assert_eq!(summons(&entities).len(), max);
+    let summoned: &EnemyKind = entities.enemies.get(summons(&entities)[0]).unwrap().kind;
+    assert_eq!(summoned, summon);
+    entities.despawn(summons(&entities)[0]);
+    run(&mut entities, 30.0, 35.0);
+    assert_eq!(summons(&entities).len(), max);
+}
+
+#[test]
+fn perception() {
+    use super::Fixture;
+    use crate::scenes::objects::enemies::enemy::enemy;
+    use crate::util::tile_center;
+
+    let fixture = Fixture::new();
+    let (world, spawn, player) = (&fixture.world, fixture.spawn, fixture.player);
+    let floor = world.floor();
+    let target = player.get_center();
+    let kind = Fixture::kind;
+    let state = |entities: &Entities, enemy: Entity| entities.enemies.get(enemy).unwrap().state;
+    let aware = |entities: &Entities, enemy: Entity| entities.enemies.get(enemy).unwrap().aware();
+    let distance = |entities: &Entities, enemy: Entity| {
+        let center = entities.rects.get(enemy).unwrap().get_center();
+        center.distance(target)
+    };
+
+    // A turret close to the player but behind a wall doesn't notice it
+    let tiles = (0..floor.room.len())
+        .flat_map(|y| (0..floor.room[y].len()).map(move |x| HashVec2::new(x as i32, y as i32)));
+    let hidden = tiles
+        .filter(|tile| pos_valid(tile, &floor.room))
+        .map(|tile| tile.to_world())
+        .find(|center| center.distance(target) < 200.0 && !world.line_of_sight(*center, target))
+        .unwrap();
+    assert!(world.line_of_sight(spawn, target));
+    let mut entities = Entities::default();
+    let turret = entities.spawn(enemy(kind("Turret"), hidden, Some(0)));
+    fixture.run(&mut entities, 0.0, 5.0, &player);
+    assert!(!aware(&entities, turret));
+    assert_eq!(entities.projectiles.iter().count(), 0);
+
+    // An enemy in sight notices it, and alerts the rest of its room
+    let shooter = entities.spawn(enemy(kind("Shooter"), spawn, Some(0)));
+    fixture.run(&mut entities, 5.0, 5.1, &player);
+    assert_eq!(state(&entities, shooter), AiState::Alert);
+    assert_eq!(state(&entities, turret), AiState::Alert);
+    fixture.run(&mut entities, 5.1, 6.0, &player);
+    assert_eq!(state(&entities, shooter), AiState::Attack);
+    assert_eq!(state(&entities, turret), AiState::Chase);
+
+    // Getting hit stuns for a moment
+    let mut commands = vec![Command::Hit {
+        entity: shooter,
+        damage: 1.0,
+    }];
+    entities.apply(&mut commands, 6.0);
+    assert_eq!(state(&entities, shooter), AiState::Stunned);
+    fixture.run(&mut entities, 6.0, 6.1, &player);
+    assert_eq!(state(&entities, shooter), AiState::Stunned);
+    fixture.run(&mut entities, 6.1, 7.0, &player);
+    assert_eq!(state(&entities, shooter), AiState::Attack);
+
+    // Too hurt to fight, it runs away
+    entities.healths.get_mut(shooter).unwrap().current = 1.0;
+    let before = distance(&entities, shooter);
+    fixture.run(&mut entities, 7.0, 8.0, &player);
+    assert_eq!(state(&entities, shooter), AiState::Flee);
+    assert!(distance(&entities, shooter) > before);
+
+    // Out of sight for long enough, the player is lost
+    let far = (floor.enemy_spawns.iter().flatten())
+        .map(|spawn| tile_center(*spawn))
+        .find(|far| far.distance(target) > 600.0)
+        .unwrap();
+    let away = Rect::new_center_vec(far, 30.0, 30.0);
+    fixture.run(&mut entities, 8.0, 12.0, &away);
+    assert!(!aware(&entities, shooter) && !aware(&entities, turret));
+}
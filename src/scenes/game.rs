@@ -1,40 +1,291 @@
 use macroquad::prelude::rand::ChooseRandom;
-use macroquad::prelude::{clear_background, WHITE};
+use macroquad::prelude::{
+    clear_background, get_char_pressed, is_key_pressed, measure_text, screen_height, screen_width,
+    set_default_camera, KeyCode, WHITE,
+};
+use macroquad::text::draw_text;
+use macroquad::window::get_internal_gl;
 
+use super::context::{Command, Context, Input, Time, World};
 use super::dungeon_manager::Manager;
-use super::object::IDObject;
+use super::entities::Entities;
+use super::object::Object;
 use super::objects::assets::load_image;
-use super::objects::enemies::enemy::Enemy;
-use super::objects::items::guns::GUNS;
-use super::objects::objects_enum::Objects;
+use super::objects::door::Door;
+use super::objects::enemies::enemy::enemy;
+use super::objects::enemies::kinds::{pick, ENEMIES};
+use super::objects::items::guns::{Gun, GUNS};
+use super::objects::items::melee::MELEES;
 use super::objects::player::main::Player;
-use super::objects::test::TestObj;
-use super::room_gen::gen::{generate_room, load_walls, Objects as RoomObjects};
+use super::room_gen::config::{GenConfig, PRESETS, SCALING};
+use super::room_gen::error::GenError;
+use super::room_gen::gen::Objects as RoomObjects;
+use super::room_gen::generators::GENERATORS;
+use super::room_gen::post::Room;
+use super::room_gen::rng::{random_seed, Rng};
+use super::save::{PlayerSave, Save};
 use crate::camera::Camera;
 use crate::scenes::objects::shapes::rect::Rect;
-use crate::util::hex;
-use crate::{pub_global_variable, repeat_for_vec, repeat_function, Object};
+use crate::unwrap_or_return;
+use crate::util::{hex, tile_center, SQUARE_SIZE};
 
-pub_global_variable!(GAME, _GAME, GameScene);
+/// Seeds tried after a random one if its dungeon can't be generated
+const RETRY_SEEDS: u64 = 10;
 
 pub struct GameScene {
     pub player: Player,
-    pub objects: Vec<Objects>,
-    pub walls: Vec<Rect>,
-    pub manager: Manager,
-    pub enemies: Vec<Enemy>,
+    pub entities: Entities,
+    pub world: World,
+    /// Asked for by the entities during the current update
+    pub commands: Vec<Command>,
+    /// Tiles of the current floor that got shot down, kept for saves
+    pub broken: Vec<(usize, usize)>,
+    /// Whether each room (in `Manager::rects`) has been cleared of enemies
+    pub cleared: Vec<bool>,
+    /// Room the player is locked in until its enemies are dead
+    pub locked_room: Option<usize>,
     pub camera: Camera,
+    pub stats: RunStats,
+    /// Whether the player took the stairs of the last floor
+    pub won: bool,
+    /// Seed being typed in by the player, `None` when not typing
+    pub seed_input: Option<String>,
+    /// Something that went wrong during the last update, shown to the player by `Play`
+    pub error: Option<String>,
 }
 impl GameScene {
     pub fn new() -> GameScene {
-        let manager = generate_room();
-        GameScene {
-            player: Player::new(),
-            objects: vec![Objects::from(TestObj::new())],
-            manager: manager.clone(),
-            walls: load_walls(&manager.room),
-            enemies: vec![Enemy::new(200.0, 200.0, 10.0)],
+        let seed = random_seed();
+        // The default config generates from any seed (see the `fuzz` test in room_gen), the
+        // following seeds are only a fallback
+        (0..RETRY_SEEDS)
+            .find_map(|offset| GameScene::from_seed(seed.wrapping_add(offset)).ok())
+            .expect("Couldn't generate a dungeon")
+    }
+
+    /// Starts a new run with a dungeon generated from `seed`
+    pub fn from_seed(seed: u64) -> Result<GameScene, GenError> {
+        let manager = Manager::new(seed, GenConfig::default())?;
+        Ok(GameScene::from_manager(manager))
+    }
+
+    fn from_manager(manager: Manager) -> GameScene {
+        let mut scene = GameScene {
+            player: Player::new(Rect::new_center_vec(
+                manager.floor().player_position(),
+                SQUARE_SIZE,
+                SQUARE_SIZE,
+            )),
+            entities: Entities::default(),
+            world: World::new(manager),
+            commands: vec![],
+            broken: vec![],
+            cleared: vec![],
+            locked_room: None,
             camera: Camera::new(),
+            stats: RunStats::default(),
+            won: false,
+            seed_input: None,
+            error: None,
+        };
+        scene.load_floor();
+        scene
+    }
+
+    /// Snapshot of the run, to be written with `save::write`
+    pub fn snapshot(&self) -> Save {
+        let player = &self.player;
+        Save {
+            seed: self.world.manager.seed,
+            preset: self.world.manager.config.name.to_owned(),
+            generator: self.world.manager.config.generator.name().to_owned(),
+            depth: self.world.manager.depth,
+            stats: self.stats,
+            cleared: self.cleared.clone(),
+            locked_room: self.locked_room,
+            broken: self.broken.clone(),
+            player: PlayerSave {
+                position: player.rect.get_center().into(),
+                health: player.health,
+                max_health: player.max_health,
+                guns: player
+                    .guns
+                    .iter()
+                    .map(|gun| (gun.name.to_owned(), gun.level))
+                    .collect(),
+                selected_gun: player.selected_gun,
+                melees: player
+                    .melees
+                    .iter()
+                    .map(|melee| melee.name.to_owned())
+                    .collect(),
+                selected_melee: player.selected_melee,
+            },
+            enemies: self.entities.enemy_saves(),
+        }
+    }
+
+    /// Picks a run back up from a snapshot, generating its floors again from the seed
+    pub fn from_save(save: &Save) -> Result<GameScene, String> {
+        let preset = PRESETS
+            .into_iter()
+            .find(|config| config.name == save.preset)
+            .ok_or(format!("unknown preset `{}`", save.preset))?;
+        let generator = GENERATORS
+            .into_iter()
+            .find(|generator| generator.name() == save.generator)
+            .ok_or(format!("unknown generator `{}`", save.generator))?;
+        let config = GenConfig {
+            generator,
+            ..preset
+        };
+
+        let generate = |err: GenError| format!("couldn't generate the floors: {}", err);
+        let mut manager = Manager::new(save.seed, config).map_err(generate)?;
+        while manager.depth < save.depth {
+            manager.next_floor().map_err(generate)?;
+        }
+        let mut scene = GameScene::from_manager(manager);
+        scene.stats = save.stats;
+
+        /* ---------------------------------- Floor --------------------------------- */
+        let rooms = scene.world.manager.floor().rects.len();
+        if save.cleared.len() != rooms {
+            return Err(format!(
+                "{} cleared rooms saved for {} rooms",
+                save.cleared.len(),
+                rooms
+            ));
+        }
+        scene.cleared = save.cleared.clone();
+
+        for (x, y) in &save.broken {
+            let tile = scene.world.floor().room.get(*y).and_then(|row| row.get(*x));
+            if !tile.is_some_and(|tile| tile.properties().destructible) {
+                return Err(format!("tile {}, {} can't be broken", x, y));
+            }
+            scene.world.manager.floor_mut().room[*y][*x] = RoomObjects::Air;
+        }
+        scene.broken = save.broken.clone();
+        scene.world.load_tiles();
+
+        if let Some(room) = save.locked_room {
+            if room >= rooms {
+                return Err(format!("locked room {} out of {} rooms", room, rooms));
+            }
+            scene.locked_room = Some(room);
+            scene.set_doors(room, false);
+        }
+
+        // Over the enemies generated with the floor, some of which were killed since
+        let seed = scene.world.floor().seed;
+        let now = Time::frame().now;
+        scene.entities.load_enemies(seed, &save.enemies, now)?;
+
+        /* --------------------------------- Player --------------------------------- */
+        let saved = &save.player;
+        let player = &mut scene.player;
+        player.guns = saved
+            .guns
+            .iter()
+            .map(|(name, level)| {
+                let gun = GUNS.iter().find(|gun| gun.name == name);
+                let gun = gun.ok_or(format!("unknown gun `{}`", name))?;
+                Ok(Gun {
+                    level: *level,
+                    ..*gun
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        player.melees = saved
+            .melees
+            .iter()
+            .map(|name| {
+                let melee = MELEES.iter().find(|melee| melee.name == name);
+                melee
+                    .copied()
+                    .ok_or(format!("unknown melee weapon `{}`", name))
+            })
+            .collect::<Result<_, String>>()?;
+        if saved.selected_gun >= player.guns.len() || saved.selected_melee >= player.melees.len() {
+            return Err("selected weapon out of the inventory".to_owned());
+        }
+        player.selected_gun = saved.selected_gun;
+        player.selected_melee = saved.selected_melee;
+        player.health = saved.health;
+        player.max_health = saved.max_health;
+
+        let position = saved.position.into();
+        player.rect.set_center_vec(position);
+        scene.camera.camera.target = position;
+        Ok(scene)
+    }
+
+    /// Rebuilds the walls, doors and enemies from the current floor and moves the player to its start room
+    fn load_floor(&mut self) {
+        self.world.load_tiles();
+        let floor = self.world.manager.floor();
+
+        self.world.doors.clear();
+        for (room, indexes) in floor.room_doors.iter().enumerate() {
+            for index in indexes {
+                let (x, y) = floor.doors[*index];
+                self.world.doors.push(Door::new(x, y, room));
+            }
+        }
+
+        self.broken.clear();
+        self.cleared = floor
+            .room_types
+            .iter()
+            .map(|room_type| room_type != &Room::Battle)
+            .collect();
+        self.locked_room = None;
+
+        /* --------------------------------- Enemies -------------------------------- */
+        // Bullets included
+        self.entities.clear(floor.seed);
+        let depth = self.world.manager.depth;
+        let count = SCALING.enemies(depth);
+        // Seeded by the floor so it always gets the same enemies
+        let mut rng = Rng::new(floor.seed);
+        for (room, spawns) in floor.enemy_spawns.iter().enumerate() {
+            for spawn in spawns.iter().take(count) {
+                let kind = pick(&mut rng, depth);
+                self.entities
+                    .spawn(enemy(kind, tile_center(*spawn), Some(room)));
+            }
+        }
+
+        /* --------------------------------- Player --------------------------------- */
+        let spawn = floor.player_position();
+        self.player.rect.set_center_vec(spawn);
+        self.camera.camera.target = spawn;
+    }
+
+    /// Turns the tile at `x`, `y` into floor
+    pub fn break_tile(&mut self, x: usize, y: usize) {
+        self.world.manager.floor_mut().room[y][x] = RoomObjects::Air;
+        self.broken.push((x, y));
+        self.world.load_tiles();
+    }
+
+    /// Goes down to the next floor once the player steps on the stairs
+    fn update_stairs(&mut self) {
+        let stairs = unwrap_or_return!(self.world.manager.floor().stairs_rect());
+        if self.player.rect.touches_rect(&stairs) {
+            if self.world.manager.depth + 1 >= SCALING.floors {
+                self.won = true;
+                return;
+            }
+            match self.world.manager.next_floor() {
+                Ok(_) => self.load_floor(),
+                Err(err) => {
+                    // Generating is deterministic so it would fail again, the stairs go away instead
+                    self.error = Some(format!("Couldn't generate the next floor: {}", err));
+                    self.world.manager.floor_mut().stairs = None;
+                }
+            }
         }
     }
 
@@ -43,35 +294,237 @@ impl GameScene {
         for gun in GUNS {
             load_image(gun.image_file).await;
         }
+        for kind in &ENEMIES {
+            load_image(kind.sheet.image_file).await;
+        }
         Player::init().await;
     }
 
-    pub fn remove_object(&mut self, id: u32) {
-        if let Some(index) = self.objects.iter().position(|x| x.get_id() == id) {
-            self.objects.remove(index);
+    /// Carries out the commands asked for during the update, once every entity is done updating
+    fn apply_commands(&mut self, time: Time) {
+        self.stats.kills += self.entities.apply(&mut self.commands, time.now);
+        for command in std::mem::take(&mut self.commands) {
+            match command {
+                Command::HitPlayer(damage) => {
+                    self.player.hit(damage, time.now);
+                }
+                Command::BreakTile(x, y) => self.break_tile(x, y),
+                Command::Shake(shake) => self.camera.set_shake(shake),
+                // Applied by `Entities::apply`
+                Command::Spawn(..) | Command::Despawn(_) | Command::Hit { .. } => {}
+            }
         }
     }
 
-    pub fn remove_enemy(&mut self, id: u32) {
-        if let Some(index) = self.enemies.iter().position(|x| x.get_id() == id) {
-            self.enemies.remove(index);
+    fn set_doors(&mut self, room: usize, open: bool) {
+        for door in &mut self.world.doors {
+            if door.room == room {
+                door.open = open;
+            }
         }
     }
+
+    /// Locks the player inside uncleared battle rooms once their enemies noticed the player, and
+    /// unlocks once all of the room's enemies are dead
+    fn update_doors(&mut self) {
+        if let Some(room) = self.locked_room {
+            if !self
+                .entities
+                .enemies
+                .iter()
+                .any(|(_, enemy)| enemy.room == Some(room))
+            {
+                self.cleared[room] = true;
+                self.stats.rooms_cleared += 1;
+                self.locked_room = None;
+                self.set_doors(room, true);
+            }
+            return;
+        }
+
+        let floor = self.world.manager.floor();
+        let room = unwrap_or_return!(floor.room_index_at(self.player.rect.get_center()));
+        if self.cleared[room] || !floor.room_interior(room).contains_rect(&self.player.rect) {
+            return;
+        }
+
+        // Rooms are only locked once one of their enemies noticed the player, who can sneak
+        // through otherwise
+        let aware = (self.entities.enemies.iter())
+            .filter(|(_, enemy)| enemy.room == Some(room))
+            .map(|(_, enemy)| enemy.aware())
+            .collect::<Vec<_>>();
+        if aware.is_empty() {
+            self.cleared[room] = true;
+        } else if aware.contains(&true) {
+            self.locked_room = Some(room);
+            self.set_doors(room, false);
+        }
+    }
+
+    /// `C` copies the current seed, `N` starts typing a seed and `Enter` restarts the run with it
+    fn update_seed(&mut self) {
+        let input = match &mut self.seed_input {
+            Some(input) => input,
+            None => {
+                if is_key_pressed(KeyCode::C) {
+                    let gl = unsafe { get_internal_gl() };
+                    gl.quad_context
+                        .clipboard_set(&self.world.manager.seed.to_string());
+                } else if is_key_pressed(KeyCode::N) {
+                    self.seed_input = Some(String::new());
+                }
+                return;
+            }
+        };
+
+        while let Some(char) = get_char_pressed() {
+            if char.is_ascii_digit() {
+                input.push(char);
+            }
+        }
+
+        if is_key_pressed(KeyCode::Backspace) {
+            input.pop();
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            self.seed_input = None;
+        } else if is_key_pressed(KeyCode::Enter) {
+            let seed = input.parse::<u64>();
+            self.seed_input = None;
+            match seed.map(GameScene::from_seed) {
+                Ok(Ok(scene)) => *self = scene,
+                Ok(Err(err)) => {
+                    self.error = Some(format!(
+                        "Couldn't generate a dungeon from that seed: {}",
+                        err
+                    ))
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    fn draw_tiles(&self) {
+        for (rect, tile) in &self.world.tiles {
+            // Drawn by the door entities and `draw_stairs`
+            if matches!(tile, RoomObjects::Door | RoomObjects::Stairs) {
+                continue;
+            }
+            rect.draw(tile.color());
+        }
+    }
+
+    fn draw_stairs(&self) {
+        let stairs = unwrap_or_return!(self.world.manager.floor().stairs_rect());
+        stairs.draw(hex("#4b3b2a"));
+
+        let mut step = stairs;
+        step.height = SQUARE_SIZE / 6.0;
+        for i in 0..3 {
+            step.pos.y = stairs.pos.y + (i as f32 + 0.5) * SQUARE_SIZE / 3.0;
+            step.draw(hex("#8a6642"));
+        }
+    }
+
+    fn draw_seed(&self) {
+        let text = match &self.seed_input {
+            Some(input) => format!("New seed: {}_", input),
+            None => format!(
+                "Floor: {}  Seed: {}",
+                self.world.manager.depth + 1,
+                self.world.manager.seed
+            ),
+        };
+
+        let width = measure_text(&text, None, 30, 1.0).width;
+        draw_text(
+            &text,
+            screen_width() - width - 10.0,
+            screen_height() - 10.0,
+            30.0,
+            WHITE,
+        );
+    }
+}
+
+/// What the player got done during a run, shown once it is over
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RunStats {
+    pub kills: usize,
+    /// Battle rooms the player was locked in and cleared
+    pub rooms_cleared: usize,
+    /// Seconds played, pauses and menus not included
+    pub time: f32,
+}
+
+impl Default for GameScene {
+    fn default() -> GameScene {
+        GameScene::new()
+    }
 }
 impl Object for GameScene {
     fn update(&mut self) {
-        repeat_for_vec!(update, self.enemies, self.objects);
-        repeat_function!(update, self.player, self.camera);
+        self.update_seed();
+        if self.seed_input.is_some() {
+            self.camera.update();
+            return;
+        }
+
+        let time = Time::frame();
+        let input = Input::capture(&self.camera);
+        let player = self.player.rect;
+        self.stats.time += time.delta;
+
+        // Every entity only changes itself, the rest goes through the commands applied after
+        self.entities
+            .update(&self.world, &player, time, &mut self.commands);
+        let mut ctx = Context::new(
+            &self.world,
+            &player,
+            &self.entities,
+            &input,
+            time,
+            &mut self.commands,
+        );
+        self.player.update(&mut ctx);
+        self.apply_commands(time);
+
+        self.camera.target = self.player.rect.get_center();
+        self.camera.update();
+        self.update_doors();
+        self.update_stairs();
     }
 
     fn draw(&mut self) {
-        clear_background(hex("#313639"));
+        // Menus drawn over the run reset the camera
+        self.camera.update_camera();
+        clear_background(RoomObjects::Air.color());
 
-        repeat_function!(draw, self.player);
-        for wall in &mut self.walls {
-            wall.draw(WHITE)
+        // Nothing gets asked for while drawing
+        let mut commands = vec![];
+        let input = Input::capture(&self.camera);
+        let player = self.player.rect;
+        let ctx = Context::new(
+            &self.world,
+            &player,
+            &self.entities,
+            &input,
+            Time::frame(),
+            &mut commands,
+        );
+
+        self.player.draw(&ctx);
+        self.draw_tiles();
+        for door in &self.world.doors {
+            door.draw();
         }
-        repeat_for_vec!(draw, self.objects, self.enemies);
-        self.player.draw_ui();
+        self.draw_stairs();
+        self.entities.draw(ctx.time.now);
+
+        // The UI is drawn in screen space
+        set_default_camera();
+        self.player.draw_ui(&ctx);
+        self.draw_seed();
     }
 }
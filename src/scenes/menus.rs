@@ -0,0 +1,365 @@
+use macroquad::prelude::{
+    clear_background, draw_rectangle, is_key_pressed, measure_text, screen_height, screen_width,
+    set_default_camera, Color, KeyCode, WHITE,
+};
+use macroquad::text::draw_text;
+use macroquad::window::get_internal_gl;
+
+use super::game::GameScene;
+use super::object::Object;
+use super::room_gen::config::SCALING;
+use super::room_gen::gen::Objects as RoomObjects;
+use super::save::{self, Save};
+use super::scene::{Scene, Transition};
+use crate::util::hex;
+
+/// Options picked with the arrow keys (or `W` and `S`) and `Enter`
+struct Menu {
+    options: Vec<&'static str>,
+    selected: usize,
+}
+impl Menu {
+    fn new(options: Vec<&'static str>) -> Menu {
+        Menu {
+            options,
+            selected: 0,
+        }
+    }
+
+    /// Option picked this frame
+    fn update(&mut self) -> Option<&'static str> {
+        let count = self.options.len();
+        if count == 0 {
+            return None;
+        }
+        if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::W) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::S) {
+            self.selected = (self.selected + 1) % count;
+        }
+
+        if is_key_pressed(KeyCode::Enter) {
+            self.options.get(self.selected).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Draws `title`, `lines` and the options centered on the screen
+    fn draw(&self, title: &str, lines: &[String]) {
+        set_default_camera();
+
+        let mut y = screen_height() / 3.0;
+        draw_centered(title, y, 80, WHITE);
+        y += 70.0;
+        for line in lines {
+            draw_centered(line, y, 30, hex("#b0b0b0"));
+            y += 35.0;
+        }
+
+        y += 30.0;
+        for (index, option) in self.options.iter().enumerate() {
+            let (text, color) = if index == self.selected {
+                (format!("> {} <", option), hex("#f2c14e"))
+            } else {
+                (option.to_string(), WHITE)
+            };
+            draw_centered(&text, y, 40, color);
+            y += 50.0;
+        }
+    }
+}
+
+fn draw_centered(text: &str, y: f32, font_size: u16, color: Color) {
+    let width = measure_text(text, None, font_size, 1.0).width;
+    draw_text(
+        text,
+        (screen_width() - width) / 2.0,
+        y,
+        font_size as f32,
+        color,
+    );
+}
+
+/// Loads the last save, `Err` is the message shown when it can't be
+fn load_save() -> Result<GameScene, String> {
+    save::read()
+        .and_then(|text| Save::parse(&text))
+        .and_then(|save| GameScene::from_save(&save))
+}
+
+/* -------------------------------- Main menu ------------------------------- */
+pub struct MainMenu {
+    menu: Menu,
+    /// Shown under the title, for saves that fail to load
+    message: Option<String>,
+    next: Option<Transition>,
+}
+impl MainMenu {
+    pub fn new() -> MainMenu {
+        MainMenu {
+            menu: Menu::new(vec![]),
+            message: None,
+            next: None,
+        }
+    }
+}
+impl Default for MainMenu {
+    fn default() -> MainMenu {
+        MainMenu::new()
+    }
+}
+impl Object for MainMenu {
+    fn update(&mut self) {
+        match self.menu.update() {
+            Some("New run") => self.next = Some(Transition::Replace(Box::new(Play::new()))),
+            Some("Continue") => match load_save() {
+                Ok(scene) => {
+                    self.next = Some(Transition::Replace(Box::new(Play::from_scene(scene))))
+                }
+                Err(err) => self.message = Some(format!("Couldn't load the save: {}", err)),
+            },
+            Some("Quit") => {
+                let gl = unsafe { get_internal_gl() };
+                gl.quad_context.order_quit();
+            }
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self) {
+        clear_background(RoomObjects::Air.color());
+        let lines = self.message.iter().cloned().collect::<Vec<_>>();
+        self.menu.draw("Rogueman", &lines);
+    }
+}
+impl Scene for MainMenu {
+    /// Only offers to continue when there is a save
+    fn enter(&mut self) {
+        let mut options = vec!["New run"];
+        if save::read().is_ok() {
+            options.push("Continue");
+        }
+        // Closing the tab does that on the web
+        if cfg!(not(target_arch = "wasm32")) {
+            options.push("Quit");
+        }
+        self.menu = Menu::new(options);
+    }
+
+    fn transition(&mut self) -> Option<Transition> {
+        self.next.take()
+    }
+}
+
+/* ---------------------------------- Play ---------------------------------- */
+/// The run being played. Ends in an `EndScreen` once the player dies or wins, `Escape` pauses it
+/// and errors of the run are shown in a `Notice`
+pub struct Play {
+    scene: GameScene,
+    next: Option<Transition>,
+}
+impl Play {
+    /// Starts a new run from a random seed
+    pub fn new() -> Play {
+        Play::from_scene(GameScene::new())
+    }
+
+    pub fn from_scene(scene: GameScene) -> Play {
+        Play { scene, next: None }
+    }
+}
+impl Default for Play {
+    fn default() -> Play {
+        Play::new()
+    }
+}
+impl Object for Play {
+    fn update(&mut self) {
+        // Escape stops typing in a seed before it pauses
+        let typing = self.scene.seed_input.is_some();
+        self.scene.update();
+
+        let game = &mut self.scene;
+        if game.player.is_dead() || game.won {
+            self.next = Some(Transition::Replace(Box::new(EndScreen::new(game))));
+        } else if let Some(error) = game.error.take() {
+            self.next = Some(Transition::Push(Box::new(Notice::new(error))));
+        } else if !typing && is_key_pressed(KeyCode::Escape) {
+            // The run doesn't change while paused, so it is saved as it was when paused
+            self.next = Some(Transition::Push(Box::new(Pause::new(game.snapshot()))));
+        }
+    }
+
+    fn draw(&mut self) {
+        self.scene.draw();
+    }
+}
+impl Scene for Play {
+    fn transition(&mut self) -> Option<Transition> {
+        self.next.take()
+    }
+}
+
+/* ---------------------------------- Pause --------------------------------- */
+pub struct Pause {
+    /// Run being paused, written by "Save"
+    save: Save,
+    menu: Menu,
+    message: Option<String>,
+    next: Option<Transition>,
+}
+impl Pause {
+    pub fn new(save: Save) -> Pause {
+        Pause {
+            save,
+            menu: Menu::new(vec!["Resume", "Save", "Main menu"]),
+            message: None,
+            next: None,
+        }
+    }
+}
+impl Object for Pause {
+    fn update(&mut self) {
+        if is_key_pressed(KeyCode::Escape) {
+            self.next = Some(Transition::Pop);
+            return;
+        }
+
+        match self.menu.update() {
+            Some("Resume") => self.next = Some(Transition::Pop),
+            Some("Save") => {
+                self.message = Some(match save::write(&self.save.to_text()) {
+                    Ok(()) => "Saved".to_owned(),
+                    Err(err) => format!("Couldn't save the run: {}", err),
+                })
+            }
+            Some("Main menu") => self.next = Some(Transition::Replace(Box::new(MainMenu::new()))),
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self) {
+        set_default_camera();
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::from_rgba(0, 0, 0, 160),
+        );
+        let lines = self.message.iter().cloned().collect::<Vec<_>>();
+        self.menu.draw("Paused", &lines);
+    }
+}
+impl Scene for Pause {
+    fn transition(&mut self) -> Option<Transition> {
+        self.next.take()
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+/* --------------------------------- Notice --------------------------------- */
+/// Message shown over the run until the player dismisses it, for errors that would otherwise go
+/// unnoticed (printing is invisible on the web)
+pub struct Notice {
+    lines: Vec<String>,
+    menu: Menu,
+    next: Option<Transition>,
+}
+impl Notice {
+    pub fn new(message: String) -> Notice {
+        Notice {
+            lines: vec![message],
+            menu: Menu::new(vec!["OK"]),
+            next: None,
+        }
+    }
+}
+impl Object for Notice {
+    fn update(&mut self) {
+        if self.menu.update().is_some() || is_key_pressed(KeyCode::Escape) {
+            self.next = Some(Transition::Pop);
+        }
+    }
+
+    fn draw(&mut self) {
+        set_default_camera();
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::from_rgba(0, 0, 0, 160),
+        );
+        self.menu.draw("Something went wrong", &self.lines);
+    }
+}
+impl Scene for Notice {
+    fn transition(&mut self) -> Option<Transition> {
+        self.next.take()
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+/* ------------------------------- End screen ------------------------------- */
+/// Death or victory screen, with a summary of the run
+pub struct EndScreen {
+    won: bool,
+    summary: Vec<String>,
+    menu: Menu,
+    next: Option<Transition>,
+}
+impl EndScreen {
+    pub fn new(game: &GameScene) -> EndScreen {
+        let stats = game.stats;
+        let time = stats.time as u32;
+        EndScreen {
+            won: game.won,
+            summary: vec![
+                format!(
+                    "Floor {} of {}",
+                    game.world.manager.depth + 1,
+                    SCALING.floors
+                ),
+                format!("Enemies killed: {}", stats.kills),
+                format!("Rooms cleared: {}", stats.rooms_cleared),
+                format!("Time: {}:{:02}", time / 60, time % 60),
+                format!("Seed: {}", game.world.manager.seed),
+            ],
+            menu: Menu::new(vec!["New run", "Main menu"]),
+            next: None,
+        }
+    }
+}
+impl Object for EndScreen {
+    fn update(&mut self) {
+        match self.menu.update() {
+            Some("New run") => self.next = Some(Transition::Replace(Box::new(Play::new()))),
+            Some("Main menu") => self.next = Some(Transition::Replace(Box::new(MainMenu::new()))),
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self) {
+        clear_background(RoomObjects::Air.color());
+        let title = if self.won {
+            "You escaped the dungeon"
+        } else {
+            "You died"
+        };
+        self.menu.draw(title, &self.summary);
+    }
+}
+impl Scene for EndScreen {
+    fn transition(&mut self) -> Option<Transition> {
+        self.next.take()
+    }
+}
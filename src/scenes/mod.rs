@@ -1,5 +1,10 @@
+pub mod context;
 pub mod dungeon_manager;
+pub mod entities;
 pub mod game;
+pub mod menus;
 pub mod object;
 pub mod objects;
 pub mod room_gen;
+pub mod save;
+pub mod scene;
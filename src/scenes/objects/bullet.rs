@@ -1,12 +1,11 @@
 use macroquad::prelude::rand::gen_range;
-use macroquad::prelude::{get_frame_time, get_time, Vec2, YELLOW};
+use macroquad::prelude::{Vec2, YELLOW};
 
 use super::shapes::rect::Rect;
-use crate::scenes::game::GAME;
-use crate::scenes::object::{obj_id, IDObject};
+use crate::scenes::entities::components::{Bundle, Faction, Projectile, Sprite, Velocity};
 use crate::util::{deg_to_rad, project};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BulletConfig {
     pub speed: f32,
     pub max_lifespan: f32,
@@ -20,87 +19,36 @@ pub struct BulletConfig {
     pub friendly: bool,
 }
 
-pub struct Bullet {
-    angle: f32,
-    rect: Rect,
-    created: f64,
-    traveled_through: u8,
-    config: BulletConfig,
-    id: u32,
-}
-impl Bullet {
-    pub fn new(angle: f32, pos: Vec2, config: BulletConfig) -> Bullet {
-        let spread = if config.spread != 0.0 {
-            deg_to_rad(gen_range(-config.spread, config.spread))
+/// Bullet fired at `now` from `pos`, towards `angle` give or take the spread
+pub fn bullet(angle: f32, pos: Vec2, config: BulletConfig, now: f64) -> Bundle {
+    let spread = if config.spread != 0.0 {
+        deg_to_rad(gen_range(-config.spread, config.spread))
+    } else {
+        0.0
+    };
+
+    Bundle {
+        rect: Some(Rect::new_center_vec(
+            pos,
+            config.bullet_size,
+            config.bullet_size,
+        )),
+        velocity: Some(Velocity(project(Vec2::ZERO, angle + spread, config.speed))),
+        sprite: Some(Sprite {
+            color: YELLOW,
+            sheet: None,
+        }),
+        faction: Some(if config.friendly {
+            Faction::Player
         } else {
-            0.0
-        };
-
-        Bullet {
-            traveled_through: 0,
-            angle: angle + spread,
-            rect: Rect::new_center_vec(pos, config.bullet_size, config.bullet_size),
-            config,
-            created: get_time(),
-            id: obj_id(),
-        }
-    }
-}
-impl Bullet {
-    fn update_collision(&mut self) {
-        for wall in &mut GAME().walls {
-            if self.rect.touches_rect(wall) {
-                GAME().remove_object(self.id);
-                return;
-            }
-        }
-
-        if self.config.friendly {
-            for enemy in &mut GAME().enemies {
-                if self.rect.touches_rect(&enemy.rect) {
-                    let success = enemy.hit(self.config.damage);
-
-                    if success {
-                        self.traveled_through += 1;
-                        if self.traveled_through > self.config.pierce {
-                            GAME().remove_object(self.id);
-                            return;
-                        }
-                    }
-                }
-            }
-        } else if self.rect.touches_rect(&GAME().player.rect) {
-            let success = GAME().player.hit(self.config.damage);
-
-            if success {
-                self.traveled_through += 1;
-                if self.traveled_through > self.config.pierce {
-                    GAME().remove_object(self.id);
-                }
-            }
-        }
-    }
-}
-impl IDObject for Bullet {
-    fn update(&mut self) {
-        if get_time() > self.created + self.config.max_lifespan as f64 {
-            GAME().remove_object(self.id);
-        }
-
-        self.rect.set_center_vec(project(
-            self.rect.get_center(),
-            self.angle,
-            self.config.speed * get_frame_time(),
-        ));
-
-        self.update_collision();
-    }
-
-    fn draw(&mut self) {
-        self.rect.draw(YELLOW);
-    }
-
-    fn get_id(&self) -> u32 {
-        self.id
+            Faction::Enemy
+        }),
+        projectile: Some(Projectile {
+            damage: config.damage,
+            pierce: config.pierce,
+            hits: 0,
+            expires: now + config.max_lifespan as f64,
+        }),
+        ..Default::default()
     }
 }
@@ -0,0 +1,40 @@
+use macroquad::prelude::Color;
+
+use super::shapes::rect::Rect;
+use crate::util::{hex, SQUARE_SIZE};
+
+pub struct Door {
+    pub rect: Rect,
+    /// Index of the room (in `Manager::rects`) the door belongs to
+    pub room: usize,
+    pub open: bool,
+}
+impl Door {
+    pub fn new(x: usize, y: usize, room: usize) -> Door {
+        Door {
+            rect: Rect::new(
+                x as f32 * SQUARE_SIZE,
+                y as f32 * SQUARE_SIZE,
+                SQUARE_SIZE,
+                SQUARE_SIZE,
+            ),
+            room,
+            open: true,
+        }
+    }
+
+    pub fn draw(&self) {
+        if self.open {
+            let mut frame = self.rect;
+            frame.height = SQUARE_SIZE / 6.0;
+            frame.draw(hex("#8a6642"));
+        } else {
+            self.rect.draw(hex("#8a6642"));
+
+            let mut bar = self.rect;
+            bar.pos.y += SQUARE_SIZE / 2.0 - SQUARE_SIZE / 12.0;
+            bar.height = SQUARE_SIZE / 6.0;
+            bar.draw(Color::from_rgba(0, 0, 0, 120));
+        }
+    }
+}
@@ -1,22 +1,71 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
 use derive_new::new;
-use macroquad::prelude::Vec2;
-use maplit::{hashmap, hashset};
-use priority_queue::PriorityQueue;
+use macroquad::prelude::{vec2, Vec2};
 
 use crate::scenes::room_gen::gen::Objects;
+use crate::util::SQUARE_SIZE;
 
-fn manhattan_heuristic(pos: &HashVec2, goal: &HashVec2) -> i32 {
-    let x_dis = pos.x.abs_diff(goal.x) as i32;
-    let y_dis = pos.y.abs_diff(goal.y) as i32;
-    -(x_dis + y_dis)
-}
+/// Cost of a step to a side, diagonal steps cost about √2 times more
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
 
-fn pos_valid(pos: &HashVec2, rooms: &Vec<Vec<Objects>>) -> bool {
-    if pos.y as usize >= rooms.len() || pos.x as usize >= rooms[pos.y as usize].len() {
+/// Sides and corners around a tile, diagonals last
+const DIRECTIONS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (-1, 1),
+    (1, -1),
+    (-1, -1),
+];
+
+/// Whether `pos` is a walkable tile of `rooms`
+pub fn pos_valid(pos: &HashVec2, rooms: &[Vec<Objects>]) -> bool {
+    if pos.x < 0 || pos.y < 0 {
         return false;
     }
+    match rooms
+        .get(pos.y as usize)
+        .and_then(|row| row.get(pos.x as usize))
+    {
+        Some(tile) => tile.properties().walkable,
+        None => false,
+    }
+}
 
-    !matches!(rooms[pos.y as usize][pos.x as usize], Objects::Wall)
+/// Walkable tiles around `tile` and the cost of stepping to them. Diagonal steps are left out
+/// unless both tiles they pass between are walkable
+pub fn neighbours(
+    tile: HashVec2,
+    rooms: &[Vec<Objects>],
+) -> impl Iterator<Item = (HashVec2, u32)> + '_ {
+    DIRECTIONS.into_iter().filter_map(move |(dx, dy)| {
+        let next = HashVec2::new(tile.x + dx, tile.y + dy);
+        if !pos_valid(&next, rooms) {
+            return None;
+        }
+        if dx == 0 || dy == 0 {
+            return Some((next, STRAIGHT));
+        }
+        let sides = [
+            HashVec2::new(tile.x + dx, tile.y),
+            HashVec2::new(tile.x, tile.y + dy),
+        ];
+        match sides.iter().all(|side| pos_valid(side, rooms)) {
+            true => Some((next, DIAGONAL)),
+            false => None,
+        }
+    })
+}
+
+/// Octile distance, the cost of the path if there were no walls
+fn octile(from: HashVec2, to: HashVec2) -> u32 {
+    let (dx, dy) = (from.x.abs_diff(to.x), from.y.abs_diff(to.y));
+    STRAIGHT * dx.max(dy) + (DIAGONAL - STRAIGHT) * dx.min(dy)
 }
 
 #[derive(Hash, PartialEq, PartialOrd, Eq, Ord, Debug, Clone, Copy, new)]
@@ -32,75 +81,191 @@ impl HashVec2 {
         }
     }
 
-    pub fn directions(&self, rooms: &Vec<Vec<Objects>>) -> Vec<HashVec2> {
-        let mut vec = vec![];
-        for pos in [
-            HashVec2::new(self.x + 1, self.y),
-            HashVec2::new(self.x - 1, self.y),
-            HashVec2::new(self.x, self.y + 1),
-            HashVec2::new(self.x, self.y - 1),
-            HashVec2::new(self.x + 1, self.y + 1),
-            HashVec2::new(self.x - 1, self.y + 1),
-            HashVec2::new(self.x + 1, self.y - 1),
-            HashVec2::new(self.x - 1, self.y - 1),
-        ] {
-            if pos_valid(&pos, rooms) {
-                vec.push(pos);
-            }
+    /// Tile the world position is on
+    pub fn from_world(pos: Vec2) -> HashVec2 {
+        HashVec2 {
+            x: (pos.x / SQUARE_SIZE).floor() as i32,
+            y: (pos.y / SQUARE_SIZE).floor() as i32,
         }
-
-        vec
     }
-}
 
-pub fn astar(start: HashVec2, goal: HashVec2, rooms: &Vec<Vec<Objects>>) -> Option<Vec<HashVec2>> {
-    if !pos_valid(&start, rooms) || !pos_valid(&goal, rooms) {
-        return None;
+    /// World position of the center of the tile, off the map for negative tiles
+    pub fn to_world(&self) -> Vec2 {
+        (vec2(self.x as f32, self.y as f32) + 0.5) * SQUARE_SIZE
     }
+}
 
-    let mut parents = hashmap! {};
-    let mut explored = hashset! {};
+/// A* search over the tiles of a room, 8 directions with octile costs. Diagonal steps can't cut
+/// the corner of a wall. The buffers are kept between searches so planning doesn't allocate once
+/// they fit the room
+#[derive(Clone, Debug, Default)]
+pub struct Pathfinder {
+    width: usize,
+    /// Cheapest cost found from the start to each tile, only set for tiles seen by this search
+    costs: Vec<u32>,
+    parents: Vec<u32>,
+    /// Search each tile was last seen or closed by, so the buffers don't need clearing
+    seen: Vec<u32>,
+    closed: Vec<u32>,
+    search: u32,
+    /// Tiles to expand by estimated total cost, then estimated cost left
+    open: BinaryHeap<Reverse<(u32, u32, u32)>>,
+}
+impl Pathfinder {
+    /// Cheapest path from `start` to `goal`, without `start`. None when the goal can't be reached,
+    /// or wasn't found within `budget` expanded tiles
+    pub fn find(
+        &mut self,
+        start: HashVec2,
+        goal: HashVec2,
+        rooms: &[Vec<Objects>],
+        budget: Option<usize>,
+    ) -> Option<Vec<HashVec2>> {
+        if !pos_valid(&start, rooms) || !pos_valid(&goal, rooms) {
+            return None;
+        }
+        self.reset(rooms);
 
-    let mut pq = PriorityQueue::<HashVec2, i32>::new();
-    pq.push(start, 0);
+        let width = self.width;
+        let index = |pos: HashVec2| pos.y as usize * width + pos.x as usize;
+        let pos = |index: usize| HashVec2::new((index % width) as i32, (index / width) as i32);
+        let (start_index, goal_index) = (index(start), index(goal));
+        self.see(start_index, 0, start_index as u32);
+        self.open.push(Reverse((
+            octile(start, goal),
+            octile(start, goal),
+            start_index as u32,
+        )));
 
-    let mut parent = HashVec2::new(0, 0);
-    while let Some(p) = pq.pop() {
-        parent = p.0;
+        let mut expanded = 0;
+        while let Some(Reverse((_, _, current))) = self.open.pop() {
+            let current = current as usize;
+            if self.closed[current] == self.search {
+                continue;
+            }
+            if current == goal_index {
+                return Some(self.path(start_index, goal_index, pos));
+            }
+            if budget.is_some_and(|budget| expanded >= budget) {
+                return None;
+            }
+            self.closed[current] = self.search;
+            expanded += 1;
 
-        if parent == goal {
-            break;
-        }
-        if explored.contains(&parent) {
-            continue;
+            for (next, step) in neighbours(pos(current), rooms) {
+                let next_index = index(next);
+                let cost = self.costs[current] + step;
+                if self.closed[next_index] == self.search
+                    || self.seen[next_index] == self.search && self.costs[next_index] <= cost
+                {
+                    continue;
+                }
+                self.see(next_index, cost, current as u32);
+                let left = octile(next, goal);
+                self.open
+                    .push(Reverse((cost + left, left, next_index as u32)));
+            }
         }
 
-        explored.insert(parent);
+        None
+    }
 
-        for child in &parent.directions(rooms) {
-            pq.push(*child, manhattan_heuristic(child, &goal));
+    /// Starts a new search, growing the buffers to fit `rooms`
+    fn reset(&mut self, rooms: &[Vec<Objects>]) {
+        self.width = rooms.iter().map(Vec::len).max().unwrap_or(0);
+        let size = self.width * rooms.len();
+        if self.costs.len() < size {
+            self.costs.resize(size, 0);
+            self.parents.resize(size, 0);
+            self.seen.resize(size, 0);
+            self.closed.resize(size, 0);
+        }
+        self.open.clear();
 
-            if !parents.contains_key(child) {
-                parents.insert(*child, parent);
-            }
+        self.search = self.search.wrapping_add(1);
+        if self.search == 0 {
+            // Tiles marked long ago would look like they were marked by this search
+            self.seen.fill(0);
+            self.closed.fill(0);
+            self.search = 1;
         }
     }
 
-    if parent != goal {
-        return None;
+    fn see(&mut self, index: usize, cost: u32, parent: u32) {
+        self.seen[index] = self.search;
+        self.costs[index] = cost;
+        self.parents[index] = parent;
     }
 
-    let mut path = vec![goal];
-    loop {
-        let grandpa = parents.get(&parent);
-        if grandpa.is_none() || grandpa.unwrap() == &start {
-            break;
+    fn path(&self, start: usize, goal: usize, pos: impl Fn(usize) -> HashVec2) -> Vec<HashVec2> {
+        let mut path = vec![];
+        let mut current = goal;
+        while current != start {
+            path.push(pos(current));
+            current = self.parents[current] as usize;
         }
-        path.insert(0, *grandpa.unwrap());
-        parent = *grandpa.unwrap();
+        path.reverse();
+        path
     }
+}
 
-    Some(path)
+/// Cheapest path from `start` to `goal`, without `start`, see `Pathfinder::find` to plan paths often
+pub fn astar(start: HashVec2, goal: HashVec2, rooms: &[Vec<Objects>]) -> Option<Vec<HashVec2>> {
+    Pathfinder::default().find(start, goal, rooms, None)
+}
+
+/// Cost of every tile from `start`, relaxing every tile until nothing changes
+#[cfg(test)]
+fn brute_force(start: HashVec2, rooms: &[Vec<Objects>]) -> Vec<Vec<Option<u32>>> {
+    let mut costs = vec![vec![None; rooms[0].len()]; rooms.len()];
+    costs[start.y as usize][start.x as usize] = Some(0);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for y in 0..rooms.len() as i32 {
+            for x in 0..rooms[0].len() as i32 {
+                let tile = HashVec2::new(x, y);
+                if !pos_valid(&tile, rooms) {
+                    continue;
+                }
+                for (dx, dy) in DIRECTIONS {
+                    let from = HashVec2::new(x + dx, y + dy);
+                    let diagonal = dx != 0 && dy != 0;
+                    let corner = |x, y| pos_valid(&HashVec2::new(x, y), rooms);
+                    if !pos_valid(&from, rooms)
+                        || diagonal && !(corner(x + dx, y) && corner(x, y + dy))
+                    {
+                        continue;
+                    }
+                    let step = if diagonal { DIAGONAL } else { STRAIGHT };
+                    let cost = match costs[from.y as usize][from.x as usize] {
+                        Some(cost) => cost + step,
+                        None => continue,
+                    };
+                    if costs[y as usize][x as usize].is_none_or(|old| cost < old) {
+                        costs[y as usize][x as usize] = Some(cost);
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+    costs
+}
+
+#[cfg(test)]
+fn random_room(rng: &mut crate::scenes::room_gen::rng::Rng, size: usize) -> Vec<Vec<Objects>> {
+    let fill = rng.gen_range(0.0, 0.45);
+    (0..size)
+        .map(|_| {
+            (0..size)
+                .map(|_| match rng.gen_range(0.0, 1.0) < fill {
+                    true => Objects::Wall,
+                    false => Objects::Air,
+                })
+                .collect()
+        })
+        .collect()
 }
 
 #[test]
@@ -111,8 +276,108 @@ fn test() {
         vec![Objects::Air, Objects::Air, Objects::Wall, Objects::Wall],
         vec![Objects::Air, Objects::Air, Objects::Wall, Objects::Air],
     ];
-    let goal = HashVec2::new(3, 3);
-    let p = HashVec2::new(0, 0);
+    let (start, goal) = (HashVec2::new(1, 1), HashVec2::new(0, 3));
+    // Going straight down and left, not through the corner of the walls
+    assert_eq!(
+        astar(start, goal, &room),
+        Some(vec![HashVec2::new(1, 2), HashVec2::new(0, 3)])
+    );
+    assert_eq!(astar(start, start, &room), Some(vec![]));
+    // Walled off, or only reachable through a corner
+    assert_eq!(astar(start, HashVec2::new(3, 3), &room), None);
+    assert_eq!(astar(start, HashVec2::new(0, 0), &room), None);
+    assert_eq!(astar(start, HashVec2::new(-1, 0), &room), None);
+
+    // Tiles and world positions round trip, off the map too
+    for tile in [start, HashVec2::new(-1, -3)] {
+        assert_eq!(HashVec2::from_world(tile.to_world()), tile);
+    }
+}
+
+#[test]
+fn matches_brute_force() {
+    use crate::scenes::room_gen::rng::Rng;
+
+    let mut rng = Rng::new(0);
+    let mut pathfinder = Pathfinder::default();
+    let mut found = 0;
+    for _ in 0..300 {
+        let size = rng.gen_range(1, 24);
+        let room = random_room(&mut rng, size);
+        let mut tile =
+            || HashVec2::new(rng.gen_range(0, size as i32), rng.gen_range(0, size as i32));
+        let (start, goal) = (tile(), tile());
+        let costs = brute_force(start, &room);
+        let cost = costs[goal.y as usize][goal.x as usize]
+            .filter(|_| pos_valid(&start, &room) && pos_valid(&goal, &room));
 
-    println!("{:?}", astar(p, goal, &room));
+        let path = pathfinder.find(start, goal, &room, None);
+        assert_eq!(
+            path.is_some(),
+            cost.is_some(),
+            "{:?} to {:?} in {:?}",
+            start,
+            goal,
+            room
+        );
+        let path = match path {
+            Some(path) => path,
+            None => continue,
+        };
+        found += 1;
+
+        // Every step goes to a walkable neighbour without cutting corners, as cheaply as possible
+        let mut total = 0;
+        let mut from = start;
+        for &to in &path {
+            let (dx, dy) = (to.x - from.x, to.y - from.y);
+            assert!(dx.abs() <= 1 && dy.abs() <= 1 && (dx, dy) != (0, 0));
+            assert!(pos_valid(&to, &room));
+            assert!(pos_valid(&HashVec2::new(to.x, from.y), &room));
+            assert!(pos_valid(&HashVec2::new(from.x, to.y), &room));
+            total += octile(from, to);
+            from = to;
+        }
+        assert_eq!(from, goal);
+        assert_eq!(Some(total), cost);
+
+        // Stops early once the budget is spent
+        if path.len() > 1 {
+            assert_eq!(pathfinder.find(start, goal, &room, Some(1)), None);
+        }
+    }
+    assert!(found > 100, "only {} paths were found", found);
+}
+
+/// Run with `cargo test --release bench -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn bench() {
+    use std::time::Instant;
+
+    use crate::scenes::room_gen::rng::Rng;
+
+    let mut rng = Rng::new(0);
+    let rooms = (0..20)
+        .map(|_| random_room(&mut rng, 100))
+        .collect::<Vec<_>>();
+    let mut pathfinder = Pathfinder::default();
+    let (mut searches, mut found) = (0, 0);
+    let time = Instant::now();
+    for room in &rooms {
+        for _ in 0..100 {
+            let mut tile = || HashVec2::new(rng.gen_range(0, 100), rng.gen_range(0, 100));
+            let (start, goal) = (tile(), tile());
+            searches += 1;
+            found += pathfinder.find(start, goal, room, None).is_some() as usize;
+        }
+    }
+    let elapsed = time.elapsed();
+    println!(
+        "{} searches on 100x100 rooms ({} found) in {:?}, {:?} per search",
+        searches,
+        found,
+        elapsed,
+        elapsed / searches
+    );
 }
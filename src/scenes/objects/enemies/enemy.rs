@@ -1,56 +1,107 @@
-use macroquad::prelude::{get_frame_time, get_time, RED};
+use macroquad::prelude::{Vec2, WHITE};
 
-use crate::scenes::game::GAME;
-use crate::scenes::object::obj_id;
+use super::kinds::{enemy_kind, EnemyKind};
+use crate::scenes::entities::components::{
+    AiState, Bundle, Collider, Enemy, Faction, Health, Sprite,
+};
 use crate::scenes::objects::shapes::rect::Rect;
-use crate::util::{angle, project, DAMAGE_COOLDOWN, SQUARE_SIZE};
-
-pub struct Enemy {
-    pub rect: Rect,
-    speed: f32,
-    pub max_health: f32,
-    pub health: f32,
-    last_damage: f64,
-    pub id: u32,
-}
-impl Enemy {
-    pub fn new(x: f32, y: f32, max_health: f32) -> Enemy {
-        Enemy {
-            rect: Rect::new_center(x, y, SQUARE_SIZE, SQUARE_SIZE),
-            speed: 100.0,
-            max_health,
-            health: max_health,
-            last_damage: f64::MIN,
-            id: obj_id(),
-        }
-    }
+use crate::scenes::save::EnemySave;
 
-    pub fn update(&mut self) {
-        self.rect.set_center_vec(project(
-            self.rect.get_center(),
-            angle(self.rect.get_center(), GAME().player.rect.get_center()),
-            self.speed * get_frame_time(),
-        ));
+/// Enemy of `kind` belonging to `room`, standing around until it notices the player
+pub fn enemy(kind: &'static EnemyKind, pos: Vec2, room: Option<usize>) -> Bundle {
+    Bundle {
+        rect: Some(Rect::new_center_vec(pos, kind.size, kind.size)),
+        health: Some(Health::new(kind.max_health)),
+        sprite: Some(Sprite {
+            color: WHITE,
+            sheet: Some(kind.sheet),
+        }),
+        faction: Some(Faction::Enemy),
+        enemy: Some(Enemy {
+            kind,
+            room,
+            last_attack: f64::MIN,
+            summoner: None,
+            state: AiState::Idle,
+            since: f64::MIN,
+            last_seen: f64::MIN,
+            home: pos,
+        }),
+        collider: Some(Collider),
+        ..Default::default()
     }
+}
 
-    pub fn draw(&mut self) {
-        self.rect.draw(RED);
-    }
+/// Enemy back from a save loaded at `now`. It is as aware of the player as when it was saved, and
+/// only just got into its state
+pub fn from_save(saved: &EnemySave, now: f64) -> Result<Bundle, String> {
+    let kind = enemy_kind(&saved.kind).ok_or(format!("unknown enemy `{}`", saved.kind))?;
+    let home = saved.home.into();
+    let state = AiState::from_name(&saved.state, home)
+        .ok_or(format!("unknown enemy state `{}`", saved.state))?;
 
-    pub fn get_id(&self) -> u32 {
-        self.id
+    let mut bundle = enemy(kind, saved.position.into(), saved.room);
+    bundle.health = Some(Health {
+        current: saved.health,
+        ..Health::new(saved.max_health)
+    });
+    if let Some(enemy) = &mut bundle.enemy {
+        enemy.home = home;
+        enemy.set_state(state, now);
+        if enemy.aware() {
+            enemy.last_seen = now;
+        }
     }
+    Ok(bundle)
+}
 
-    pub fn hit(&mut self, damage: f32) -> bool {
-        if get_time() <= self.last_damage + DAMAGE_COOLDOWN {
-            return false;
-        }
+#[test]
+fn reload_into_locked_room() {
+    use crate::scenes::save::Save;
 
-        self.last_damage = get_time();
-        self.health -= damage;
-        if self.health < 0.0 {
-            GAME().remove_enemy(self.id);
-        }
-        true
+    // A version 3 save, from before enemy states were saved
+    let text = "rogueman save 3
+seed 1
+preset Medium
+generator bsp
+depth 1
+stats 0 0 0
+cleared 0 0
+locked 1
+player 0 0 100 100
+gun 1 Pistol
+selected_gun 0
+melee Pocket Knife
+selected_melee 0
+enemy Shooter 1400 900 8 8 1
+enemy Charger 200 300 10 10 0
+";
+    let save = Save::parse(text).unwrap();
+    let enemies = save
+        .enemies
+        .iter()
+        .map(|saved| from_save(saved, 12.0).unwrap().enemy.unwrap())
+        .collect::<Vec<_>>();
+    // Still after the player in the locked room, so the room stays locked and fought in
+    assert_eq!(enemies[0].state, AiState::Chase);
+    assert!(enemies[0].aware());
+    assert_eq!((enemies[0].since, enemies[0].last_seen), (12.0, 12.0));
+    assert_eq!(enemies[1].state, AiState::Idle);
+    assert!(!enemies[1].aware());
+
+    // And the same after saving them again
+    let mut save = save;
+    for (saved, enemy) in save.enemies.iter_mut().zip(&enemies) {
+        saved.state = enemy.state.name().to_owned();
+        saved.home = enemy.home.into();
     }
+    let save = Save::parse(&save.to_text()).unwrap();
+    let again = from_save(&save.enemies[0], 30.0).unwrap().enemy.unwrap();
+    assert_eq!(again.state, AiState::Chase);
+    assert_eq!(again.home, Vec2::new(1400.0, 900.0));
+    assert_eq!(again.last_seen, 30.0);
+
+    let mut bad = save.enemies[0].clone();
+    bad.state = "dancing".to_owned();
+    assert!(from_save(&bad, 0.0).is_err());
 }
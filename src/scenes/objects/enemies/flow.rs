@@ -0,0 +1,138 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::astar::{neighbours, pos_valid, HashVec2};
+use crate::scenes::room_gen::gen::Objects;
+
+/// Cost of the cheapest path from every tile to a goal, with the same costs as `astar`. Computed
+/// once for everyone heading to the goal, each of them only has to look at the tiles around it
+#[derive(Clone, Debug, Default)]
+pub struct FlowField {
+    width: usize,
+    goal: Option<HashVec2>,
+    /// `u32::MAX` for the tiles the field didn't reach
+    costs: Vec<u32>,
+    open: BinaryHeap<Reverse<(u32, u32)>>,
+}
+impl FlowField {
+    /// Goal of the last `compute`
+    pub fn goal(&self) -> Option<HashVec2> {
+        self.goal
+    }
+
+    /// Spreads out from `goal` through the walkable tiles `allowed` lets in
+    pub fn compute(
+        &mut self,
+        goal: HashVec2,
+        rooms: &[Vec<Objects>],
+        allowed: impl Fn(HashVec2) -> bool,
+    ) {
+        self.goal = Some(goal);
+        self.width = rooms.iter().map(Vec::len).max().unwrap_or(0);
+        self.costs.clear();
+        self.costs.resize(self.width * rooms.len(), u32::MAX);
+        self.open.clear();
+        if !pos_valid(&goal, rooms) {
+            return;
+        }
+
+        let width = self.width;
+        let index = |pos: HashVec2| pos.y as usize * width + pos.x as usize;
+        self.costs[index(goal)] = 0;
+        self.open.push(Reverse((0, index(goal) as u32)));
+        while let Some(Reverse((cost, current))) = self.open.pop() {
+            let current = current as usize;
+            if cost > self.costs[current] {
+                continue;
+            }
+
+            let tile = HashVec2::new((current % width) as i32, (current / width) as i32);
+            for (next, step) in neighbours(tile, rooms) {
+                let next_index = index(next);
+                if cost + step >= self.costs[next_index] || !allowed(next) {
+                    continue;
+                }
+                self.costs[next_index] = cost + step;
+                self.open.push(Reverse((cost + step, next_index as u32)));
+            }
+        }
+    }
+
+    /// Cost of the path from `tile` to the goal, None if the field didn't reach it
+    pub fn cost(&self, tile: HashVec2) -> Option<u32> {
+        if tile.x < 0 || tile.y < 0 || tile.x as usize >= self.width {
+            return None;
+        }
+        let cost = *self
+            .costs
+            .get(tile.y as usize * self.width + tile.x as usize)?;
+        (cost != u32::MAX).then_some(cost)
+    }
+
+    /// Tile around `tile` one step closer to the goal, None on the goal or where the field didn't
+    /// reach
+    pub fn next(&self, tile: HashVec2, rooms: &[Vec<Objects>]) -> Option<HashVec2> {
+        let cost = self.cost(tile)?;
+        neighbours(tile, rooms)
+            .filter_map(|(next, step)| Some((next, self.cost(next)? + step)))
+            .filter(|(_, total)| *total == cost)
+            .map(|(next, _)| next)
+            .next()
+    }
+
+    /// Tile around `tile` the furthest from the goal, None if every tile around is closer or the
+    /// field didn't reach it
+    pub fn away(&self, tile: HashVec2, rooms: &[Vec<Objects>]) -> Option<HashVec2> {
+        let cost = self.cost(tile)?;
+        neighbours(tile, rooms)
+            .filter_map(|(next, _)| Some((next, self.cost(next)?)))
+            .filter(|(_, next_cost)| *next_cost > cost)
+            .max_by_key(|(_, next_cost)| *next_cost)
+            .map(|(next, _)| next)
+    }
+}
+
+#[test]
+fn test() {
+    use super::astar::astar;
+
+    let room = vec![
+        vec![Objects::Air, Objects::Air, Objects::Air, Objects::Air],
+        vec![Objects::Wall, Objects::Wall, Objects::Air, Objects::Wall],
+        vec![Objects::Air, Objects::Air, Objects::Air, Objects::Air],
+        vec![Objects::Air, Objects::Wall, Objects::Wall, Objects::Air],
+    ];
+    let goal = HashVec2::new(0, 0);
+    let mut field = FlowField::default();
+    field.compute(goal, &room, |_| true);
+
+    // Following the field costs as much as the path found by `astar`
+    for y in 0..4 {
+        for x in 0..4 {
+            let mut tile = HashVec2::new(x, y);
+            let path = astar(tile, goal, &room);
+            assert_eq!(field.cost(tile).is_some(), path.is_some());
+            if path.is_none() {
+                continue;
+            }
+
+            let mut followed = vec![];
+            while let Some(next) = field.next(tile, &room) {
+                followed.push(next);
+                tile = next;
+            }
+            assert_eq!(tile, goal);
+            assert_eq!(followed.len(), path.unwrap().len());
+        }
+    }
+
+    // Tiles left out block the way like walls
+    field.compute(goal, &room, |tile| tile != HashVec2::new(2, 1));
+    assert_eq!(field.cost(HashVec2::new(3, 0)), Some(30));
+    assert_eq!(field.cost(HashVec2::new(2, 2)), None);
+    assert_eq!(field.next(goal, &room), None);
+
+    // Running away goes down the corridor, and stops at its end
+    assert_eq!(field.away(goal, &room), Some(HashVec2::new(1, 0)));
+    assert_eq!(field.away(HashVec2::new(3, 0), &room), None);
+}
@@ -0,0 +1,251 @@
+use crate::scenes::entities::components::Sheet;
+use crate::scenes::objects::bullet::BulletConfig;
+use crate::scenes::room_gen::rng::Rng;
+
+/// What an enemy does on top of walking to the player
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Behaviour {
+    /// Speeds up `charge` times once within `range` of the player, until it gets to it
+    Charger { range: f32, charge: f32 },
+    /// Stops within `range` of the player to shoot at it every `fire_delay` seconds
+    Shooter {
+        range: f32,
+        fire_delay: f32,
+        bullet: BulletConfig,
+    },
+    /// Blows up once within `radius` of the player, dealing `damage` to it
+    Exploder { radius: f32, damage: f32 },
+    /// Stops within `range` of the player to call in a `summon` every `delay` seconds, with at most
+    /// `max` of its summons alive at once
+    Summoner {
+        range: f32,
+        delay: f32,
+        max: usize,
+        summon: &'static EnemyKind,
+    },
+    /// Shoots `shots` bullets around itself every `fire_delay` seconds while the player is within
+    /// `range`, the first one at the player. Turrets don't move
+    Turret {
+        range: f32,
+        fire_delay: f32,
+        shots: u8,
+        bullet: BulletConfig,
+    },
+}
+impl Behaviour {
+    /// Distance to the player the enemy starts attacking from
+    pub fn range(&self) -> f32 {
+        match *self {
+            Behaviour::Charger { range, .. }
+            | Behaviour::Shooter { range, .. }
+            | Behaviour::Summoner { range, .. }
+            | Behaviour::Turret { range, .. } => range,
+            Behaviour::Exploder { radius, .. } => radius,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnemyKind {
+    /// Written in saves, a single word
+    pub name: &'static str,
+    pub sheet: Sheet,
+    pub max_health: f32,
+    /// Pixels per second
+    pub speed: f32,
+    /// Side of the enemy, at most a tile to fit through corridors
+    pub size: f32,
+    /// Damage dealt to the player when touching it
+    pub contact_damage: f32,
+    /// How far it sees the player from, when nothing is in the way
+    pub view: f32,
+    /// Share of its max health it runs away under, 0 for kinds that never do
+    pub flee_health: f32,
+    pub behaviour: Behaviour,
+    /// Chance of spawning against the other kinds, on each floor from the first one. The last
+    /// weight is used for the floors after
+    pub weights: &'static [f32],
+}
+impl EnemyKind {
+    /// Spawn weight on the floor at `depth`
+    pub fn weight(&self, depth: usize) -> f32 {
+        match self.weights.get(depth).or(self.weights.last()) {
+            Some(weight) => *weight,
+            None => 0.0,
+        }
+    }
+}
+
+const CHARGER: EnemyKind = EnemyKind {
+    name: "Charger",
+    sheet: Sheet {
+        image_file: "./assets/enemies/charger.png",
+        frames: 4,
+        frame_duration: 0.15,
+    },
+    max_health: 10.0,
+    speed: 90.0,
+    size: 30.0,
+    contact_damage: 10.0,
+    view: 300.0,
+    flee_health: 0.0,
+    behaviour: Behaviour::Charger {
+        range: 150.0,
+        charge: 2.5,
+    },
+    weights: &[4.0, 4.0, 3.0],
+};
+
+const SHOOTER: EnemyKind = EnemyKind {
+    name: "Shooter",
+    sheet: Sheet {
+        image_file: "./assets/enemies/shooter.png",
+        frames: 4,
+        frame_duration: 0.2,
+    },
+    max_health: 8.0,
+    speed: 70.0,
+    size: 26.0,
+    contact_damage: 5.0,
+    view: 350.0,
+    flee_health: 0.3,
+    behaviour: Behaviour::Shooter {
+        range: 250.0,
+        fire_delay: 1.2,
+        bullet: BulletConfig {
+            speed: 350.0,
+            max_lifespan: 3.0,
+            spread: 5.0,
+            bullet_size: 10.0,
+            pierce: 0,
+            damage: 10.0,
+            friendly: false,
+        },
+    },
+    weights: &[2.0, 3.0],
+};
+
+const EXPLODER: EnemyKind = EnemyKind {
+    name: "Exploder",
+    sheet: Sheet {
+        image_file: "./assets/enemies/exploder.png",
+        frames: 4,
+        frame_duration: 0.1,
+    },
+    max_health: 5.0,
+    speed: 130.0,
+    size: 24.0,
+    contact_damage: 0.0,
+    view: 250.0,
+    flee_health: 0.0,
+    behaviour: Behaviour::Exploder {
+        radius: 45.0,
+        damage: 25.0,
+    },
+    weights: &[0.0, 1.0, 2.0, 2.0, 3.0],
+};
+
+const SUMMONER: EnemyKind = EnemyKind {
+    name: "Summoner",
+    sheet: Sheet {
+        image_file: "./assets/enemies/summoner.png",
+        frames: 4,
+        frame_duration: 0.25,
+    },
+    max_health: 20.0,
+    speed: 50.0,
+    size: 30.0,
+    contact_damage: 5.0,
+    view: 350.0,
+    flee_health: 0.5,
+    behaviour: Behaviour::Summoner {
+        range: 300.0,
+        delay: 4.0,
+        max: 3,
+        summon: &CHARGER,
+    },
+    weights: &[0.0, 0.0, 1.0, 1.0, 2.0],
+};
+
+const TURRET: EnemyKind = EnemyKind {
+    name: "Turret",
+    sheet: Sheet {
+        image_file: "./assets/enemies/turret.png",
+        frames: 4,
+        frame_duration: 0.3,
+    },
+    max_health: 25.0,
+    speed: 0.0,
+    size: 30.0,
+    contact_damage: 0.0,
+    view: 350.0,
+    flee_health: 0.0,
+    behaviour: Behaviour::Turret {
+        range: 350.0,
+        fire_delay: 2.0,
+        shots: 8,
+        bullet: BulletConfig {
+            speed: 250.0,
+            max_lifespan: 3.0,
+            spread: 0.0,
+            bullet_size: 10.0,
+            pierce: 0,
+            damage: 10.0,
+            friendly: false,
+        },
+    },
+    weights: &[0.0, 1.0, 1.0, 2.0],
+};
+
+pub static ENEMIES: [EnemyKind; 5] = [CHARGER, SHOOTER, EXPLODER, SUMMONER, TURRET];
+
+/// Kind of enemy called `name`, for saves
+pub fn enemy_kind(name: &str) -> Option<&'static EnemyKind> {
+    ENEMIES.iter().find(|kind| kind.name == name)
+}
+
+/// Kind of enemy to spawn on the floor at `depth`, picked by weight
+pub fn pick(rng: &mut Rng, depth: usize) -> &'static EnemyKind {
+    let total = ENEMIES.iter().map(|kind| kind.weight(depth)).sum::<f32>();
+    let mut roll = rng.gen_range(0.0, total);
+    for kind in &ENEMIES {
+        if roll < kind.weight(depth) {
+            return kind;
+        }
+        roll -= kind.weight(depth);
+    }
+    &ENEMIES[0]
+}
+
+#[test]
+fn catalogue() {
+    use crate::scenes::room_gen::config::SCALING;
+    use crate::util::SQUARE_SIZE;
+
+    for kind in &ENEMIES {
+        assert_eq!(enemy_kind(kind.name), Some(kind));
+        assert!(!kind.name.contains(char::is_whitespace), "{}", kind.name);
+        assert!(kind.size <= SQUARE_SIZE, "{}", kind.name);
+        // Seen coming before it attacks
+        assert!(kind.view >= kind.behaviour.range(), "{}", kind.name);
+        assert!((0.0..1.0).contains(&kind.flee_health), "{}", kind.name);
+        if let Behaviour::Summoner { summon, .. } = kind.behaviour {
+            assert_eq!(enemy_kind(summon.name), Some(summon));
+        }
+        if let Behaviour::Shooter { bullet, .. } | Behaviour::Turret { bullet, .. } = kind.behaviour
+        {
+            assert!(!bullet.friendly, "{}", kind.name);
+        }
+    }
+
+    // Every floor has something to spawn, and kinds without weight on a floor never show up there
+    let mut rng = Rng::new(0);
+    for depth in 0..SCALING.floors + 2 {
+        assert!(ENEMIES.iter().any(|kind| kind.weight(depth) > 0.0));
+        for _ in 0..200 {
+            assert!(pick(&mut rng, depth).weight(depth) > 0.0);
+        }
+    }
+    let first = (0..500).map(|_| pick(&mut rng, 0).name).collect::<Vec<_>>();
+    assert!(first.contains(&"Charger") && first.contains(&"Shooter"));
+}
@@ -1,2 +1,4 @@
 pub mod astar;
 pub mod enemy;
+pub mod flow;
+pub mod kinds;
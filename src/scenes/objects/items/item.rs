@@ -7,3 +7,15 @@ pub enum Rarities {
     Legendary,
     Unique,
 }
+impl Rarities {
+    /// One rarity higher, used when loot rolls higher on deeper floors
+    pub fn next(&self) -> Rarities {
+        match self {
+            Rarities::Common => Rarities::Uncommon,
+            Rarities::Uncommon => Rarities::Rare,
+            Rarities::Rare => Rarities::Epic,
+            Rarities::Epic => Rarities::Legendary,
+            Rarities::Legendary | Rarities::Unique => *self,
+        }
+    }
+}
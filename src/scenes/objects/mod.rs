@@ -1,8 +1,7 @@
 pub mod assets;
 pub mod bullet;
+pub mod door;
 pub mod enemies;
 pub mod items;
-pub mod objects_enum;
 pub mod player;
 pub mod shapes;
-pub mod test;
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 
 use derive_new::new;
-use macroquad::prelude::{draw_rectangle, draw_texture, get_time, screen_height, Color, WHITE};
+use macroquad::prelude::{draw_rectangle, draw_texture, screen_height, Color, WHITE};
 use maplit::hashmap;
 
+use crate::repeat_function;
+use crate::scenes::context::Context;
 use crate::scenes::objects::assets::{get_image, get_image_owned, load_image, load_image_owned};
 use crate::scenes::objects::items::guns::{Gun, GUNS};
 use crate::scenes::objects::items::melee::{Melee, MELEES};
@@ -11,14 +13,11 @@ use crate::scenes::objects::shapes::line::Line;
 use crate::scenes::objects::shapes::rect::Rect;
 use crate::spritesheet::SpriteSheet;
 use crate::util::{
-    multiline_text, rx_smooth, ry_smooth, Direction, CARDINAL_DIRECTIONS, DAMAGE_COOLDOWN,
-    DIRECTIONS, SQUARE_SIZE,
+    multiline_text, Direction, CARDINAL_DIRECTIONS, DAMAGE_COOLDOWN, DIRECTIONS, SQUARE_SIZE,
 };
-use crate::{repeat_function, GAME};
 
 #[derive(Debug, new)]
 pub struct Player {
-    #[new(value = "Rect::new_center(-100.0, -100.0, SQUARE_SIZE, SQUARE_SIZE)")]
     pub rect: Rect,
     #[new(value = "500.0")]
     pub speed: f32,
@@ -132,10 +131,10 @@ impl Player {
         }
     }
 
-    pub fn update(&mut self) {
-        self.update_movement();
-        self.update_shoot();
-        self.update_melee();
+    pub fn update(&mut self, ctx: &mut Context) {
+        self.update_movement(ctx);
+        self.update_shoot(ctx);
+        self.update_melee(ctx);
 
         /* ----------------------------- Drawing sprite ----------------------------- */
         self.move_spritesheets
@@ -148,11 +147,9 @@ impl Player {
                 .unwrap()
                 .update();
         }
-
-        GAME().camera.target = self.rect.get_center();
     }
 
-    pub fn draw(&mut self) {
+    pub fn draw(&mut self, ctx: &Context) {
         let center = self.rect.get_center();
 
         /* ----------------------------- Drawing sprite ----------------------------- */
@@ -170,16 +167,18 @@ impl Player {
             );
         }
 
-        self.draw_melee();
+        self.draw_melee(ctx);
     }
 
-    pub fn draw_ui(&self) {
+    /// Drawn in screen space, over the run
+    pub fn draw_ui(&self, ctx: &Context) {
         /* ------------------------------- Debug Menu ------------------------------- */
         let gun = self.get_gun();
         let melee = self.get_melee();
+        let room = ctx.world.floor().room_type_at(self.rect.get_center());
         multiline_text(
             &format!(
-                "X,Y: {}, {}\nGun: {}\nMelee: {}",
+                "X,Y: {}, {}\nGun: {}\nMelee: {}\nRoom: {}",
                 self.rect.get_center().x.round(),
                 self.rect.get_center().y.round(),
                 if gun.is_none() {
@@ -192,17 +191,21 @@ impl Player {
                 } else {
                     melee.unwrap().name
                 },
+                match room {
+                    Some(room) => format!("{:?}", room),
+                    None => "None".to_owned(),
+                },
             ),
-            rx_smooth(0.0),
-            ry_smooth(27.0),
+            0.0,
+            27.0,
             50,
             WHITE,
         );
 
         /* -------------------------------- Gun info -------------------------------- */
         if let Some(g) = gun {
-            let x = rx_smooth(10.0);
-            let y = ry_smooth(screen_height() - 74.0);
+            let x = 10.0;
+            let y = screen_height() - 74.0;
 
             /* --------------------------------- Border --------------------------------- */
             let border_texture = get_image("./assets/guns/border.png");
@@ -215,7 +218,7 @@ impl Player {
             /* ---------------------------- Shooting cooldown --------------------------- */
             let fire_delay = g.fire_delay as f64;
             let ratio =
-                ((get_time() - self.last_shot).clamp(0.0, fire_delay) - fire_delay) / fire_delay;
+                ((ctx.time.now - self.last_shot).clamp(0.0, fire_delay) - fire_delay) / fire_delay;
             draw_rectangle(
                 x,
                 y,
@@ -226,17 +229,18 @@ impl Player {
         }
     }
 
-    pub fn hit(&mut self, damage: f32) -> bool {
-        if self.invulnerable || get_time() <= self.last_damage + DAMAGE_COOLDOWN {
+    pub fn hit(&mut self, damage: f32, now: f64) -> bool {
+        if self.invulnerable || now <= self.last_damage + DAMAGE_COOLDOWN {
             return false;
         }
 
-        self.last_damage = get_time();
+        self.last_damage = now;
         self.health -= damage;
-        if self.health < 0.0 {
-            println!("Player died");
-        }
-
         true
     }
+
+    /// Out of health, the run ends (see `menus::Play`)
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0.0
+    }
 }
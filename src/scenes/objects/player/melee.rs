@@ -1,27 +1,29 @@
-use macroquad::prelude::{get_time, is_key_pressed, is_mouse_button_pressed, MouseButton, YELLOW};
+use macroquad::prelude::{MouseButton, YELLOW};
 
 use super::main::Player;
+use crate::scenes::context::{Command, Context};
+use crate::scenes::entities::components::Faction;
 use crate::scenes::objects::items::melee::Melee;
 use crate::scenes::objects::shapes::line::Line;
-use crate::util::{angle, project, rel_mouse_pos, Direction, NUMBER_KEYS, ROLL_ANGLES};
-use crate::{unwrap_or_return, GAME};
+use crate::util::{angle, project, Direction, NUMBER_KEYS, ROLL_ANGLES};
+use crate::{unwrap_or_continue, unwrap_or_return};
 
 impl Player {
-    pub fn update_melee(&mut self) {
+    pub fn update_melee(&mut self, ctx: &mut Context) {
         let melee = unwrap_or_return!(self.get_melee());
 
         for i in 1..=3 {
             let key = NUMBER_KEYS[i - 1];
-            if is_key_pressed(key) && i < self.melees.len() {
+            if ctx.input.key_pressed(key) && i < self.melees.len() {
                 self.selected_melee = i;
             }
         }
 
-        let (mut swinging, on_cooldown) = self.melee_info(melee);
+        let (mut swinging, on_cooldown) = self.melee_info(melee, ctx.time.now);
 
         /* ----------------------------- Start of swing ----------------------------- */
-        if !on_cooldown && is_mouse_button_pressed(MouseButton::Left) {
-            self.last_melee_angle = Some(angle(self.rect.get_center(), rel_mouse_pos()));
+        if !on_cooldown && ctx.input.button_pressed(MouseButton::Left) {
+            self.last_melee_angle = Some(angle(self.rect.get_center(), ctx.input.mouse));
             self.last_melee_line = Some(Line::new(
                 self.rect.get_center(),
                 project(
@@ -32,7 +34,7 @@ impl Player {
                 melee.range_width,
             ));
 
-            self.last_melee = get_time();
+            self.last_melee = ctx.time.now;
 
             // Calculate nearest direction of swing
             let mut nearest_angle = 360.0;
@@ -62,34 +64,38 @@ impl Player {
             );
 
             // Hitting enemies
-            for enemy in &mut GAME().enemies {
-                if line.touches_rect(&enemy.rect) {
-                    enemy.hit(melee.damage);
+            let entities = ctx.entities;
+            for (entity, _) in entities.healths.iter() {
+                let rect = unwrap_or_continue!(entities.rects.get(entity));
+                if entities.factions.get(entity) != Some(&Faction::Player)
+                    && line.touches_rect(rect)
+                {
+                    ctx.commands.push(Command::Hit {
+                        entity,
+                        damage: melee.damage,
+                    });
                 }
             }
         }
     }
 
-    pub fn draw_melee(&mut self) {
+    pub fn draw_melee(&mut self, ctx: &Context) {
         let melee = unwrap_or_return!(self.get_melee());
 
-        let (swinging, _) = self.melee_info(melee);
+        let (swinging, _) = self.melee_info(melee, ctx.time.now);
         if swinging {
             self.last_melee_line.as_ref().unwrap().draw(YELLOW);
         }
     }
 
     /// Returns `(swinging, on_cooldown)`
-    fn melee_info(&mut self, melee: Melee) -> (bool, bool) {
+    fn melee_info(&mut self, melee: Melee, now: f64) -> (bool, bool) {
         if self.last_melee == 0.0
-            || get_time() > self.last_melee + melee.delay as f64 + melee.swing_duration as f64
+            || now > self.last_melee + melee.delay as f64 + melee.swing_duration as f64
         {
             (false, false)
         } else {
-            (
-                get_time() <= self.last_melee + melee.swing_duration as f64,
-                true,
-            )
+            (now <= self.last_melee + melee.swing_duration as f64, true)
         }
     }
 
@@ -2,34 +2,35 @@ use std::collections::HashMap;
 use std::f32::consts::PI;
 
 use lazy_static::lazy_static;
-use macroquad::prelude::{get_frame_time, get_time, is_key_down, is_key_pressed, vec2, KeyCode};
+use macroquad::prelude::{vec2, KeyCode};
 use maplit::hashmap;
 
 use super::main::Player;
-use crate::scenes::game::GAME;
+use crate::scenes::context::Context;
 use crate::scenes::objects::shapes::rect::Rect;
 use crate::util::{deg_to_rad, project, Direction, ROLL_ANGLES};
 
 impl Player {
-    pub fn update_movement(&mut self) {
+    pub fn update_movement(&mut self, ctx: &mut Context) {
         self.hspd = 0.0;
         self.vspd = 0.0;
 
         /* -------------------------------- Movement -------------------------------- */
-        if is_key_down(KeyCode::W) {
+        if ctx.input.key_down(KeyCode::W) {
             self.vspd -= 1.0
         }
-        if is_key_down(KeyCode::S) {
+        if ctx.input.key_down(KeyCode::S) {
             self.vspd += 1.0
         }
-        if is_key_down(KeyCode::A) {
+        if ctx.input.key_down(KeyCode::A) {
             self.hspd -= 1.0
         }
-        if is_key_down(KeyCode::D) {
+        if ctx.input.key_down(KeyCode::D) {
             self.hspd += 1.0
         }
 
-        let speed = self.speed * get_frame_time();
+        let tile = ctx.world.floor().tile_at(self.rect.get_center());
+        let speed = self.speed * tile.properties().speed * ctx.time.delta;
 
         self.hspd *= speed;
         self.vspd *= speed;
@@ -40,11 +41,11 @@ impl Player {
             self.vspd *= 0.707;
         }
 
-        self.rolling();
+        self.rolling(ctx);
 
         /* --------------------------- Collision detection -------------------------- */
         self.rect.pos.x += self.hspd;
-        for wall in &GAME().walls {
+        for wall in ctx.world.walls.iter().chain(ctx.world.closed_doors()) {
             if self.rect.touches_rect(wall) {
                 if self.rect.pos.x > wall.pos.x {
                     self.rect.set_left(wall.get_right());
@@ -55,7 +56,7 @@ impl Player {
         }
 
         self.rect.pos.y += self.vspd;
-        for wall in &GAME().walls {
+        for wall in ctx.world.walls.iter().chain(ctx.world.closed_doors()) {
             if self.rect.touches_rect(wall) {
                 if self.rect.pos.y > wall.pos.y {
                     self.rect.set_top(wall.get_bottom());
@@ -65,6 +66,17 @@ impl Player {
             }
         }
 
+        /* ---------------------------------- Tiles --------------------------------- */
+        let damage = ctx
+            .world
+            .floor()
+            .tile_at(self.rect.get_center())
+            .properties()
+            .damage;
+        if damage > 0.0 {
+            self.hit(damage, ctx.time.now);
+        }
+
         /* ------------------------------ Set direction ----------------------------- */
         self.update_direction();
     }
@@ -100,12 +112,13 @@ impl Player {
         };
     }
 
-    fn rolling(&mut self) {
-        self.rolling = get_time() <= self.last_roll + self.roll_duration as f64;
+    fn rolling(&mut self, ctx: &Context) {
+        let now = ctx.time.now;
+        self.rolling = now <= self.last_roll + self.roll_duration as f64;
         let on_cooldown =
-            get_time() <= self.last_roll + self.roll_duration as f64 + self.roll_cooldown as f64;
+            now <= self.last_roll + self.roll_duration as f64 + self.roll_cooldown as f64;
 
-        if !on_cooldown && !self.rolling && is_key_pressed(KeyCode::Space) {
+        if !on_cooldown && !self.rolling && ctx.input.key_pressed(KeyCode::Space) {
             self.roll_angle = *ROLL_ANGLES
                 .get(if self.hspd == 0.0 && self.vspd == 0.0 {
                     &Direction::W
@@ -113,7 +126,7 @@ impl Player {
                     &self.direction
                 })
                 .unwrap();
-            self.last_roll = get_time();
+            self.last_roll = now;
             self.rolling = true;
         }
 
@@ -121,7 +134,7 @@ impl Player {
             let pos = project(
                 vec2(0.0, 0.0),
                 self.roll_angle,
-                self.roll_speed * get_frame_time(),
+                self.roll_speed * ctx.time.delta,
             );
             self.hspd = pos.x;
             self.vspd = -pos.y;
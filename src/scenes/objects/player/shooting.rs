@@ -1,21 +1,18 @@
-use macroquad::prelude::{
-    get_time, is_key_pressed, is_mouse_button_down, is_mouse_button_pressed, KeyCode, MouseButton,
-};
+use macroquad::prelude::{KeyCode, MouseButton};
 
 use super::main::Player;
-use crate::scenes::game::GAME;
-use crate::scenes::objects::bullet::Bullet;
+use crate::scenes::context::{Command, Context};
+use crate::scenes::objects::bullet::bullet;
 use crate::scenes::objects::items::guns::Gun;
-use crate::scenes::objects::objects_enum::Objects;
 use crate::unwrap_or_return;
-use crate::util::{angle, rel_mouse_pos};
+use crate::util::angle;
 
 impl Player {
-    pub fn update_shoot(&mut self) {
+    pub fn update_shoot(&mut self, ctx: &mut Context) {
         let gun = unwrap_or_return!(self.get_gun());
 
         /* ---------------------------- Switching weapons --------------------------- */
-        if is_key_pressed(KeyCode::G) {
+        if ctx.input.key_pressed(KeyCode::G) {
             self.selected_gun += 1;
             if self.selected_gun >= self.guns.len() {
                 self.selected_gun = 0;
@@ -24,27 +21,29 @@ impl Player {
 
         /* -------------------------------- Shooting -------------------------------- */
         if if gun.holdable {
-            is_mouse_button_down(MouseButton::Right)
+            ctx.input.button_down(MouseButton::Right)
         } else {
-            is_mouse_button_pressed(MouseButton::Right)
+            ctx.input.button_pressed(MouseButton::Right)
         } {
-            self.shoot();
+            self.shoot(ctx);
         }
     }
 
-    fn shoot(&mut self) {
+    fn shoot(&mut self, ctx: &mut Context) {
         let gun = unwrap_or_return!(self.get_gun());
 
-        if self.last_shot == 0.0 || get_time() > self.last_shot + gun.fire_delay as f64 {
-            GAME().camera.set_shake(gun.shake);
+        let now = ctx.time.now;
+        if self.last_shot == 0.0 || now > self.last_shot + gun.fire_delay as f64 {
+            ctx.commands.push(Command::Shake(gun.shake));
 
-            let angle = angle(self.rect.get_center(), rel_mouse_pos());
-            GAME().objects.push(Objects::from(Bullet::new(
+            let angle = angle(self.rect.get_center(), ctx.input.mouse);
+            ctx.spawn(bullet(
                 angle,
                 self.rect.get_center(),
                 gun.bullet_config,
-            )));
-            self.last_shot = get_time();
+                now,
+            ));
+            self.last_shot = now;
         }
     }
 
@@ -79,6 +79,14 @@ impl Rect {
             && self.get_bottom() > rect.pos.y
     }
 
+    /// Whether `rect` is completely inside this rect
+    pub fn contains_rect(&self, rect: &Rect) -> bool {
+        rect.get_left() >= self.get_left()
+            && rect.get_right() <= self.get_right()
+            && rect.get_top() >= self.get_top()
+            && rect.get_bottom() <= self.get_bottom()
+    }
+
     pub fn touches_line(&self, line: &mut Line) -> bool {
         line.touches_rect(self)
     }
@@ -0,0 +1,175 @@
+use super::error::GenError;
+use super::generators::{Generator, BSP, CAVES, STITCHER, TUNNELS};
+
+/// Smallest map (in tiles) rooms and corridors can be fit in
+pub const MIN_SIZE: usize = 24;
+/// Biggest map (in tiles), keeps a typo from asking for gigabytes of tiles
+pub const MAX_SIZE: usize = 1000;
+/// Smallest split limit (in tiles), smaller sections can't hold a room with its walls and doors
+pub const MIN_SPLIT_LIMIT: usize = 8;
+
+/// Parameters read by every room_gen stage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenConfig {
+    pub name: &'static str,
+    /// Algorithm laying out the rooms
+    pub generator: Generator,
+    /// Width and height of the map (in tiles)
+    pub size: usize,
+    /// Sections with a side shorter than this (in tiles) are not split any further
+    pub split_limit: usize,
+    /// Where along a section the split line can fall, as a ratio of its length
+    pub split_ratio: (f32, f32),
+    /// How much rooms are shrunk inside their section, as a ratio of its size
+    pub shrink_range: (f32, f32),
+    /// Inside width of corridors (in tiles), odd widths keep doors centered
+    pub path_size: usize,
+    /// Chance (`0.0` - `1.0`) of a room getting a prefab obstacle
+    pub prefab_density: f32,
+    /// Chance (`0.0` - `1.0`) of two nearby rooms that aren't connected getting an extra
+    /// corridor, making loops in the layout
+    pub loop_chance: f32,
+    /// Most hidden rooms behind destructible walls on a floor, they only go next to dead ends
+    /// with enough empty space around them
+    pub secret_rooms: usize,
+}
+
+pub const SMALL: GenConfig = GenConfig {
+    name: "Small",
+    generator: BSP,
+    size: 120,
+    split_limit: 40,
+    split_ratio: (0.3, 0.7),
+    shrink_range: (0.7, 0.9),
+    path_size: 3,
+    prefab_density: 0.5,
+    loop_chance: 0.3,
+    secret_rooms: 1,
+};
+
+pub const MEDIUM: GenConfig = GenConfig {
+    name: "Medium",
+    generator: BSP,
+    size: 200,
+    split_limit: 66,
+    split_ratio: (0.3, 0.7),
+    shrink_range: (0.7, 0.9),
+    path_size: 3,
+    prefab_density: 1.0,
+    loop_chance: 0.3,
+    secret_rooms: 2,
+};
+
+pub const HUGE: GenConfig = GenConfig {
+    name: "Huge",
+    generator: BSP,
+    size: 300,
+    split_limit: 60,
+    split_ratio: (0.35, 0.65),
+    shrink_range: (0.75, 0.9),
+    path_size: 5,
+    prefab_density: 1.0,
+    loop_chance: 0.4,
+    secret_rooms: 3,
+};
+
+pub const PRESETS: [GenConfig; 3] = [SMALL, MEDIUM, HUGE];
+
+impl GenConfig {
+    /// Checks the values the generators rely on, so a bad config is an error instead of a panic
+    pub fn validate(&self) -> Result<(), GenError> {
+        let invalid = |reason: String| Err(GenError::InvalidConfig(reason));
+        // Comparisons with NaN are always false, so NaN is out of range too
+        let in_range = |(low, high): (f32, f32), max: f32| low > 0.0 && low <= high && high <= max;
+
+        if !(MIN_SIZE..=MAX_SIZE).contains(&self.size) {
+            return invalid(format!(
+                "size {} isn't between {} and {}",
+                self.size, MIN_SIZE, MAX_SIZE
+            ));
+        }
+        if self.split_limit < MIN_SPLIT_LIMIT {
+            return invalid(format!(
+                "split limit {} is under {}",
+                self.split_limit, MIN_SPLIT_LIMIT
+            ));
+        }
+        if !in_range(self.split_ratio, 1.0) || self.split_ratio.1 == 1.0 {
+            return invalid(format!(
+                "split ratio {:?} isn't an increasing range inside 0 - 1",
+                self.split_ratio
+            ));
+        }
+        if !in_range(self.shrink_range, 1.0) {
+            return invalid(format!(
+                "shrink range {:?} isn't an increasing range inside 0 - 1",
+                self.shrink_range
+            ));
+        }
+        if self.path_size == 0 {
+            return invalid("path size is 0".to_owned());
+        }
+        Ok(())
+    }
+}
+
+impl Default for GenConfig {
+    fn default() -> GenConfig {
+        MEDIUM
+    }
+}
+
+/// How floors change the deeper the player goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloorScaling {
+    /// Floors in a run, taking the stairs of the last one wins it
+    pub floors: usize,
+    /// Tiles added to the map size every floor, the split limit stays the same so deeper floors have more rooms
+    pub size_per_floor: usize,
+    pub max_size: usize,
+    /// Extra enemies per battle room, added every floor
+    pub enemies_per_floor: f32,
+    /// Chance added every floor of loot rolling one rarity higher
+    pub rarity_per_floor: f32,
+    /// Generators cycled through floor after floor, starting from the one of the first floor.
+    /// The first floor's generator is used for every floor when it isn't in the list
+    pub biomes: &'static [Generator],
+}
+impl FloorScaling {
+    /// Config for the floor at `depth` (`0` being the first floor)
+    pub fn config(&self, base: &GenConfig, depth: usize) -> GenConfig {
+        let size = (base.size + self.size_per_floor * depth).min(self.max_size.max(base.size));
+        let generator = match self
+            .biomes
+            .iter()
+            .position(|biome| biome == &base.generator)
+        {
+            Some(first) => self.biomes[(first + depth) % self.biomes.len()],
+            None => base.generator,
+        };
+        GenConfig {
+            size,
+            generator,
+            ..*base
+        }
+    }
+
+    /// Amount of enemies in each battle room on the floor at `depth`
+    pub fn enemies(&self, depth: usize) -> usize {
+        (1.0 + self.enemies_per_floor * depth as f32).floor() as usize
+    }
+
+    /// Chance of loot rolling one rarity higher on the floor at `depth`
+    pub fn rarity_chance(&self, depth: usize) -> f32 {
+        (self.rarity_per_floor * depth as f32).min(1.0)
+    }
+}
+
+pub const SCALING: FloorScaling = FloorScaling {
+    floors: 5,
+    size_per_floor: 20,
+    max_size: 300,
+    enemies_per_floor: 0.5,
+    rarity_per_floor: 0.1,
+    biomes: &[BSP, CAVES, STITCHER, TUNNELS],
+};
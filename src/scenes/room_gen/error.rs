@@ -0,0 +1,49 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// Why a floor couldn't be generated
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GenError {
+    /// A config value the generators can't work with
+    InvalidConfig(String),
+    /// A generator tried to put a tile outside of the map
+    OutOfBounds { x: i32, y: i32, size: usize },
+    /// A layout refers to a room that doesn't exist
+    MissingRoom(usize),
+    /// A layout refers to a door that doesn't exist
+    MissingDoor(usize),
+    /// Not enough rooms for a start and an end room
+    TooFewRooms(usize),
+    /// Every attempt left these rooms out of reach of the start room
+    Unreachable(Vec<usize>),
+    /// No open tile for the stairs or the player spawn
+    NoSpace(&'static str),
+}
+impl Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenError::InvalidConfig(reason) => write!(f, "invalid config: {}", reason),
+            GenError::OutOfBounds { x, y, size } => {
+                write!(
+                    f,
+                    "tile ({}, {}) is outside of the {}x{} map",
+                    x, y, size, size
+                )
+            }
+            GenError::MissingRoom(index) => write!(f, "room {} doesn't exist", index),
+            GenError::MissingDoor(index) => write!(f, "door {} doesn't exist", index),
+            GenError::TooFewRooms(count) => {
+                write!(
+                    f,
+                    "only {} rooms were laid out, at least 2 are needed",
+                    count
+                )
+            }
+            GenError::Unreachable(rooms) => {
+                write!(f, "rooms {:?} can't be reached from the start room", rooms)
+            }
+            GenError::NoSpace(what) => write!(f, "no open tile left for the {}", what),
+        }
+    }
+}
+impl Error for GenError {}
@@ -0,0 +1,224 @@
+use std::fmt::Write;
+
+use macroquad::prelude::{Color, Image, GREEN, RED};
+
+use super::gen::Objects;
+use super::post::Room;
+use super::validate::interior;
+use crate::scenes::dungeon_manager::{Floor, Manager};
+use crate::util::hex;
+
+/// Tiles left between floors in `manager_image`
+const FLOOR_GAP: usize = 4;
+
+/// Letter marking the center of a room in the ASCII export
+fn room_symbol(room: Room) -> char {
+    match room {
+        Room::Start => 'S',
+        Room::End => 'E',
+        Room::Battle => 'B',
+        Room::Treasure => 'T',
+        Room::Shop => '$',
+        Room::Secret => '?',
+    }
+}
+
+/// Tint of the floor of a room in the image export
+fn room_color(room: Room) -> Color {
+    match room {
+        Room::Start => hex("#2f4a36"),
+        Room::End => hex("#43344f"),
+        Room::Battle => hex("#4a3131"),
+        Room::Treasure => hex("#4d4527"),
+        Room::Shop => hex("#2c3f52"),
+        Room::Secret => hex("#3c4a4a"),
+    }
+}
+
+/// The floor's tiles (see `Objects::symbol`) with `@` on the player spawn, `e` on the enemy spawns
+/// and the type of every room at its center, followed by a list of the rooms with their distance
+/// from the start room and whether they are on the critical path
+pub fn floor_ascii(floor: &Floor) -> String {
+    let mut grid = floor
+        .room
+        .iter()
+        .map(|row| row.iter().map(Objects::symbol).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    for spawn in floor.enemy_spawns.iter().flatten() {
+        grid[spawn.1][spawn.0] = 'e';
+    }
+    if let Some((x, y)) = floor.player_spawn {
+        grid[y][x] = '@';
+    }
+    for (rect, room_type) in floor.rects.iter().zip(&floor.room_types) {
+        let (x, y) = (rect.get_center().x as usize, rect.get_center().y as usize);
+        if grid[y][x] == Objects::Air.symbol() {
+            grid[y][x] = room_symbol(*room_type);
+        }
+    }
+
+    let mut text = String::new();
+    for row in grid {
+        text.extend(row);
+        text.push('\n');
+    }
+
+    let critical_path = floor.critical_path();
+    for (index, (rect, room_type)) in floor.rects.iter().zip(&floor.room_types).enumerate() {
+        let distance = match floor.start_distance(index) {
+            Some(distance) => distance.to_string(),
+            None => "unreachable".to_owned(),
+        };
+        writeln!(
+            text,
+            "Room {}: {:?} at ({}, {}) {}x{}, {} doors, {} enemy spawns, distance {}{}",
+            index,
+            room_type,
+            rect.pos.x,
+            rect.pos.y,
+            rect.width,
+            rect.height,
+            floor.room_doors[index].len(),
+            floor.enemy_spawns.get(index).map_or(0, Vec::len),
+            distance,
+            if critical_path.contains(&index) {
+                ", critical"
+            } else {
+                ""
+            },
+        )
+        .unwrap();
+    }
+
+    text
+}
+
+/// Every floor of the run, each with a header
+pub fn manager_ascii(manager: &Manager) -> String {
+    let mut text = format!("Seed: {}\n", manager.seed);
+    for (depth, floor) in manager.floors.iter().enumerate() {
+        writeln!(
+            text,
+            "\nFloor {} (seed {}, {}x{})",
+            depth + 1,
+            floor.seed,
+            floor.room.len(),
+            floor.room.len()
+        )
+        .unwrap();
+        text.push_str(&floor_ascii(floor));
+    }
+    text
+}
+
+/// Fills a `scale` by `scale` square of `image`, with `y` going down
+fn set_tile(image: &mut Image, (x, y): (usize, usize), scale: usize, color: Color) {
+    for py in y * scale..(y + 1) * scale {
+        for px in x * scale..(x + 1) * scale {
+            // `Image::export_png` flips the image vertically
+            let flipped = image.height as usize - py - 1;
+            image.set_pixel(px as u32, flipped as u32, color);
+        }
+    }
+}
+
+/// Pixels taken by `tiles` tiles at `scale` pixels per tile, an error past the biggest image side
+fn image_side(tiles: usize, scale: usize) -> Result<u16, String> {
+    tiles
+        .checked_mul(scale)
+        .and_then(|pixels| u16::try_from(pixels).ok())
+        .ok_or(format!(
+            "{} tiles at {} pixels per tile is bigger than an image can be ({} pixels)",
+            tiles,
+            scale,
+            u16::MAX
+        ))
+}
+
+/// The floor with `scale` pixels per tile, rooms tinted by type and the spawns marked
+pub fn floor_image(floor: &Floor, scale: usize) -> Result<Image, String> {
+    let size = image_side(floor.room.len(), scale)?;
+    let mut image = Image::gen_image_color(size, size, Objects::Air.color());
+    draw_floor(&mut image, floor, 0, scale);
+    Ok(image)
+}
+
+/// Draws `floor` into `image`, `left` tiles from its left edge
+fn draw_floor(image: &mut Image, floor: &Floor, left: usize, scale: usize) {
+    let offset = |(x, y): (usize, usize)| (x + left, y);
+
+    for (y, row) in floor.room.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            set_tile(image, offset((x, y)), scale, tile.color());
+        }
+    }
+
+    for (rect, room_type) in floor.rects.iter().zip(&floor.room_types) {
+        for (x, y) in interior(rect) {
+            if floor.room[y][x] == Objects::Air {
+                set_tile(image, offset((x, y)), scale, room_color(*room_type));
+            }
+        }
+    }
+
+    for spawn in floor.enemy_spawns.iter().flatten() {
+        set_tile(image, offset(*spawn), scale, RED);
+    }
+    if let Some(spawn) = floor.player_spawn {
+        set_tile(image, offset(spawn), scale, GREEN);
+    }
+}
+
+/// Every floor of the run side by side
+pub fn manager_image(manager: &Manager, scale: usize) -> Result<Image, String> {
+    let width = manager
+        .floors
+        .iter()
+        .map(|floor| floor.room.len() + FLOOR_GAP)
+        .sum::<usize>()
+        - FLOOR_GAP;
+    let height = manager
+        .floors
+        .iter()
+        .map(|floor| floor.room.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut image = Image::gen_image_color(
+        image_side(width, scale)?,
+        image_side(height, scale)?,
+        hex("#1d2022"),
+    );
+    let mut offset = 0;
+    for floor in &manager.floors {
+        draw_floor(&mut image, floor, offset, scale);
+        offset += floor.room.len() + FLOOR_GAP;
+    }
+    Ok(image)
+}
+
+#[test]
+fn export() {
+    use super::config::SMALL;
+
+    let mut manager = Manager::new(3, SMALL).unwrap();
+    manager.next_floor().unwrap();
+
+    let ascii = manager_ascii(&manager);
+    assert!(ascii.starts_with("Seed: 3\n"));
+    assert_eq!(ascii.matches('@').count(), 2);
+    assert!(ascii.contains("Floor 2"));
+
+    let floors = &manager.floors;
+    let image = manager_image(&manager, 2).unwrap();
+    assert_eq!(
+        image.width as usize,
+        (floors[0].room.len() + FLOOR_GAP + floors[1].room.len()) * 2
+    );
+    assert_eq!(image.height as usize, floors[1].room.len() * 2);
+
+    // Too big to fit in an image
+    assert!(manager_image(&manager, 1_000).is_err());
+    assert!(floor_image(&floors[0], usize::MAX).is_err());
+}
@@ -3,44 +3,179 @@ use std::sync::Mutex;
 
 use derive_new::new;
 use lazy_static::lazy_static;
-use macroquad::prelude::load_string;
-use maplit::{hashmap, hashset};
+use macroquad::prelude::{load_string, Color, WHITE};
+use maplit::hashmap;
 
-use super::paths::paths;
+use super::config::{GenConfig, PRESETS};
+use super::error::GenError;
+use super::generators::{Generate, Layout};
+use super::graph::RoomGraph;
+use super::loops::add_loops;
 use super::post::post;
+use super::rng::Rng;
+use super::secrets::add_secret_rooms;
+use super::spawns::{enemy_spawns, player_spawn};
 use super::util::print_room;
-use crate::scenes::dungeon_manager::Manager;
+use super::validate::{check_layout, open_tile, repair, unreachable_rooms};
+use crate::scenes::dungeon_manager::Floor;
 use crate::scenes::objects::shapes::rect::Rect;
-use crate::scenes::room_gen::init::init_rects;
-use crate::scenes::room_gen::util::{draw_rect, find_rect, point_valid, rand_rect};
-use crate::util::SQUARE_SIZE;
+use crate::util::{hex, SQUARE_SIZE};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Objects {
+    /// Plain floor
     Air,
     Wall,
+    /// Can't be walked over, but can be shot across
+    Pit,
+    /// Hurts whoever stands on it
+    Spikes,
+    /// Slows down whoever walks through it
+    Water,
+    /// Blocks like a wall until it is shot
+    Crate,
+    /// Looks like a wall, but breaks when shot like a crate. Hides the way to secret rooms
+    SecretWall,
+    /// Doorway of a room, the `Door` entity on it does the blocking
+    Door,
+    /// Leads down to the next floor
+    Stairs,
 }
+impl Objects {
+    pub const ALL: [Objects; 9] = [
+        Objects::Air,
+        Objects::Wall,
+        Objects::Pit,
+        Objects::Spikes,
+        Objects::Water,
+        Objects::Crate,
+        Objects::SecretWall,
+        Objects::Door,
+        Objects::Stairs,
+    ];
 
+    pub fn properties(&self) -> TileProperties {
+        let floor = TileProperties {
+            walkable: true,
+            blocks_bullets: false,
+            blocks_sight: false,
+            damage: 0.0,
+            speed: 1.0,
+            destructible: false,
+        };
+        let solid = TileProperties {
+            walkable: false,
+            blocks_bullets: true,
+            blocks_sight: true,
+            ..floor
+        };
+
+        match self {
+            Objects::Air | Objects::Door | Objects::Stairs => floor,
+            Objects::Wall => solid,
+            Objects::Crate | Objects::SecretWall => TileProperties {
+                destructible: true,
+                ..solid
+            },
+            Objects::Pit => TileProperties {
+                walkable: false,
+                ..floor
+            },
+            Objects::Spikes => TileProperties {
+                damage: 1.0,
+                ..floor
+            },
+            Objects::Water => TileProperties {
+                speed: 0.5,
+                ..floor
+            },
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Objects::Air => hex("#313639"),
+            Objects::Wall => WHITE,
+            Objects::Pit => hex("#16191a"),
+            Objects::Spikes => hex("#9a3b3b"),
+            Objects::Water => hex("#2f5f8a"),
+            Objects::Crate => hex("#a07a4a"),
+            // Barely off from the walls, only a careful look gives it away
+            Objects::SecretWall => hex("#f2f2f2"),
+            Objects::Door => hex("#8a6642"),
+            Objects::Stairs => hex("#4b3b2a"),
+        }
+    }
+
+    /// Character used for the tile in prefabs and when printing rooms
+    pub fn symbol(&self) -> char {
+        match self {
+            Objects::Air => ' ',
+            Objects::Wall => '#',
+            Objects::Pit => 'o',
+            Objects::Spikes => '^',
+            Objects::Water => '~',
+            Objects::Crate => 'x',
+            Objects::SecretWall => '%',
+            Objects::Door => '+',
+            Objects::Stairs => '>',
+        }
+    }
+
+    /// Tile written as `symbol`, None for unknown symbols
+    pub fn from_symbol(symbol: char) -> Option<Objects> {
+        Objects::ALL
+            .into_iter()
+            .find(|tile| tile.symbol() == symbol)
+    }
+}
+
+/// How a tile interacts with whatever is on or going through it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileProperties {
+    pub walkable: bool,
+    pub blocks_bullets: bool,
+    pub blocks_sight: bool,
+    /// Damage dealt to the player standing on the tile
+    pub damage: f32,
+    /// Movement speed multiplier on the tile
+    pub speed: f32,
+    /// Breaks into air when shot
+    pub destructible: bool,
+}
+
+/// World space rect of the tile at `x`, `y`
+pub fn tile_rect(x: usize, y: usize) -> Rect {
+    Rect::new(
+        x as f32 * SQUARE_SIZE,
+        y as f32 * SQUARE_SIZE,
+        SQUARE_SIZE,
+        SQUARE_SIZE,
+    )
+}
+
+/// Rects of the tiles that can't be walked through
 pub fn load_walls(room: &[Vec<Objects>]) -> Vec<Rect> {
-    let mut walls: Vec<Rect> = vec![];
+    load_tiles(room)
+        .into_iter()
+        .filter(|(_, tile)| !tile.properties().walkable)
+        .map(|(rect, _)| rect)
+        .collect()
+}
+
+/// Rects of every tile that isn't air
+pub fn load_tiles(room: &[Vec<Objects>]) -> Vec<(Rect, Objects)> {
+    let mut tiles = vec![];
 
     for (y, line) in room.iter().enumerate() {
         for (x, obj) in line.iter().enumerate() {
-            match obj {
-                Objects::Air => {}
-                Objects::Wall => {
-                    walls.push(Rect::new(
-                        x as f32 * SQUARE_SIZE,
-                        y as f32 * SQUARE_SIZE,
-                        SQUARE_SIZE,
-                        SQUARE_SIZE,
-                    ));
-                }
+            if obj != &Objects::Air {
+                tiles.push((tile_rect(x, y), *obj));
             }
         }
     }
 
-    walls
+    tiles
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -57,48 +192,167 @@ pub struct SplitQueue {
     pub y_limits: (usize, usize),
 }
 
-pub fn generate_room() -> Manager {
-    let size = 200;
-    let split_limit = size / 3;
+/// Layouts that fail or still have unreachable rooms after repairing are regenerated, up to this
+/// many times
+const MAX_ATTEMPTS: u64 = 20;
 
-    /* ------------------------------- Init rects ------------------------------- */
-    let mut room = init_rects(size, split_limit);
+/// Generates a dungeon, the same `seed` and `config` always yield the same `Floor`
+pub fn generate_room(seed: u64, config: &GenConfig) -> Result<Floor, GenError> {
+    config.validate()?;
 
-    /* -------------------------- Detecting rectangles -------------------------- */
-    let mut rects = vec![];
-    let mut explored = hashset! {};
-    for (y, row) in room.iter().enumerate() {
-        for (x, _) in row.iter().enumerate() {
-            if !point_valid(&(x, y), &room) || explored.contains(&(x, y)) {
-                continue;
-            }
-
-            let rect = find_rect((x, y), &room, &mut explored).unwrap();
-
-            rects.push(rect);
+    let mut result = generate_connected(seed, 0, config);
+    for attempt in 1..MAX_ATTEMPTS {
+        if result.is_ok() {
+            break;
         }
+        result = generate_connected(seed, attempt, config);
     }
+    let mut floor = result?;
+
+    let mut rng = Rng::new(seed);
+    add_secret_rooms(&mut floor, config, &mut rng)?;
+
+    /* --------------------------------- Stairs --------------------------------- */
+    floor.stairs = floor
+        .end_room()
+        .and_then(|end| open_tile(&floor.rects[end], &floor.room));
+    let (x, y) = floor.stairs.ok_or(GenError::NoSpace("stairs"))?;
+    floor.room[y][x] = Objects::Stairs;
+
+    /* --------------------------------- Spawns --------------------------------- */
+    floor.player_spawn = Some(player_spawn(&floor).ok_or(GenError::NoSpace("player spawn"))?);
+    floor.enemy_spawns = enemy_spawns(&floor, &mut rng);
 
-    /* ---------------------------- Random sub rects ---------------------------- */
-    for rect in &mut rects {
-        rand_rect(rect);
+    Ok(floor)
+}
+
+/// Generates the floor for one attempt, erroring if rooms are still unreachable after repairing
+fn generate_connected(seed: u64, attempt: u64, config: &GenConfig) -> Result<Floor, GenError> {
+    let attempt_seed = seed ^ attempt.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut floor = generate_attempt(attempt_seed, config)?;
+    floor.seed = seed;
+
+    if !repair(&mut floor) {
+        return Err(GenError::Unreachable(unreachable_rooms(&floor)));
     }
+    Ok(floor)
+}
 
-    /* ------------------------------ Drawing rects ----------------------------- */
-    room = vec![vec![Objects::Air; size]; size];
-    for rect in &rects {
-        draw_rect(rect, &mut room);
+fn generate_attempt(seed: u64, config: &GenConfig) -> Result<Floor, GenError> {
+    let mut rng = Rng::new(seed);
+
+    let mut layout = config.generator.layout(config, &mut rng)?;
+    if config.generator.empty_between_rooms() {
+        add_loops(&mut layout, config, &mut rng)?;
+    }
+    check_layout(&layout, config.size)?;
+    if layout.rects.len() < 2 {
+        return Err(GenError::TooFewRooms(layout.rects.len()));
     }
 
-    /* ---------------------------- Pathways and post --------------------------- */
-    let doors = paths(&rects, size, &mut room);
-    post(&mut room, &mut rects);
+    let Layout {
+        mut room,
+        mut rects,
+        doors,
+        room_doors,
+        adjacents,
+    } = layout;
+    let graph = RoomGraph::new(&adjacents, rects.len());
+    let room_types = post(
+        &mut room,
+        &mut rects,
+        &graph,
+        (&doors, &room_doors),
+        config,
+        &mut rng,
+    );
 
-    Manager { room, doors }
+    Ok(Floor {
+        room,
+        doors,
+        room_doors,
+        rects,
+        room_types,
+        graph,
+        stairs: None,
+        player_spawn: None,
+        enemy_spawns: vec![],
+        seed,
+    })
 }
 
 #[test]
 fn test() {
-    let room = generate_room();
+    let room = generate_room(0, &GenConfig::default()).unwrap();
     print_room(&room.room);
 }
+
+#[test]
+fn seeded() {
+    for config in &PRESETS {
+        for seed in [0, 1, 1234, u64::MAX] {
+            assert_eq!(generate_room(seed, config), generate_room(seed, config));
+        }
+        assert_ne!(
+            generate_room(1, config).unwrap().room,
+            generate_room(2, config).unwrap().room
+        );
+    }
+}
+
+#[test]
+fn tiles() {
+    for tile in Objects::ALL {
+        assert_eq!(Objects::from_symbol(tile.symbol()), Some(tile));
+    }
+
+    let floor = generate_room(0, &GenConfig::default()).unwrap();
+    let (x, y) = floor.stairs.unwrap();
+    assert_eq!(floor.room[y][x], Objects::Stairs);
+    assert!(floor
+        .doors
+        .iter()
+        .all(|(x, y)| floor.room[*y][*x] == Objects::Door));
+}
+
+#[test]
+fn fuzz() {
+    use super::generators::GENERATORS;
+    use super::validate::unreachable_rooms;
+
+    // Random configs, valid or not, have to give a floor or an error without panicking
+    let mut rng = Rng::new(0);
+    let range = |rng: &mut Rng| {
+        let (a, b) = (rng.gen_range(-0.05, 1.05), rng.gen_range(-0.05, 1.05));
+        (a.min(b), a.max(b))
+    };
+
+    let mut generated = 0;
+    for seed in 0..2000 {
+        let config = GenConfig {
+            name: "Fuzz",
+            generator: *rng.choose(&GENERATORS),
+            size: rng.gen_range(0, 100),
+            split_limit: rng.gen_range(0, 50),
+            split_ratio: range(&mut rng),
+            shrink_range: range(&mut rng),
+            path_size: rng.gen_range(0, 8),
+            prefab_density: rng.gen_range(0.0, 1.0),
+            loop_chance: rng.gen_range(0.0, 1.0),
+            secret_rooms: rng.gen_range(0, 4),
+        };
+        let name = format!("{:?} seed {}", config, seed);
+
+        match generate_room(seed, &config) {
+            Ok(floor) => {
+                generated += 1;
+                assert_eq!(unreachable_rooms(&floor), vec![], "{}", name);
+                assert!(floor.stairs.is_some(), "{}", name);
+                assert!(floor.player_spawn.is_some(), "{}", name);
+            }
+            Err(GenError::InvalidConfig(_)) => assert!(config.validate().is_err(), "{}", name),
+            Err(_) => assert!(config.validate().is_ok(), "{}", name),
+        }
+    }
+    assert!(generated > 500, "only {} floors were generated", generated);
+}
@@ -0,0 +1,61 @@
+use maplit::hashset;
+
+use super::{Generate, Layout};
+use crate::scenes::room_gen::config::GenConfig;
+use crate::scenes::room_gen::error::GenError;
+use crate::scenes::room_gen::gen::Objects;
+use crate::scenes::room_gen::init::init_rects;
+use crate::scenes::room_gen::paths::{paths, AdjacentRects};
+use crate::scenes::room_gen::rng::Rng;
+use crate::scenes::room_gen::util::{draw_rect, find_rect, point_valid, rand_rect};
+
+/// Binary space partitioning, the map is split into sections and each gets a shrunk room,
+/// neighbouring rooms are joined with straight corridors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bsp;
+impl Generate for Bsp {
+    fn layout(&self, config: &GenConfig, rng: &mut Rng) -> Result<Layout, GenError> {
+        let size = config.size;
+
+        /* ------------------------------- Init rects ------------------------------- */
+        let mut room = init_rects(config, rng);
+
+        /* -------------------------- Detecting rectangles -------------------------- */
+        let mut rects = vec![];
+        let mut explored = hashset! {};
+        for (y, row) in room.iter().enumerate() {
+            for (x, _) in row.iter().enumerate() {
+                if !point_valid(&(x, y), &room) || explored.contains(&(x, y)) {
+                    continue;
+                }
+
+                // The point is valid so there always is a rect around it
+                if let Some(rect) = find_rect((x, y), &room, &mut explored) {
+                    rects.push(rect);
+                }
+            }
+        }
+
+        /* ---------------------------- Random sub rects ---------------------------- */
+        for rect in &mut rects {
+            rand_rect(rect, config, rng);
+        }
+
+        /* ------------------------------ Drawing rects ----------------------------- */
+        room = vec![vec![Objects::Air; size]; size];
+        for rect in &rects {
+            draw_rect(rect, &mut room)?;
+        }
+
+        /* -------------------------------- Pathways -------------------------------- */
+        let mut layout = Layout {
+            room,
+            room_doors: vec![vec![]; rects.len()],
+            rects,
+            doors: vec![],
+            adjacents: AdjacentRects::new(),
+        };
+        paths(&mut layout, config)?;
+        Ok(layout)
+    }
+}
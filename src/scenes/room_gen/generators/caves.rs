@@ -0,0 +1,96 @@
+use super::enclose::{enclose_rooms, keep_largest};
+use super::{Generate, Layout};
+use crate::scenes::room_gen::config::GenConfig;
+use crate::scenes::room_gen::error::GenError;
+use crate::scenes::room_gen::gen::Objects;
+use crate::scenes::room_gen::rng::Rng;
+
+/// Tiles per side of a cave cell, keeps passages at least this wide
+const CELL: usize = 2;
+
+/// Cellular automata caves, random noise is smoothed into caverns and rooms are walled in
+/// wherever there's enough open ground
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Caves {
+    /// Chance of a cell starting out as a wall
+    pub fill: f32,
+    /// Smoothing passes over the noise
+    pub steps: usize,
+}
+impl Caves {
+    /// Amount of walls around a cell, outside the map counts as wall
+    fn walls(cells: &[Vec<Objects>], x: usize, y: usize) -> usize {
+        let mut walls = 0;
+        for ny in y as i32 - 1..=y as i32 + 1 {
+            for nx in x as i32 - 1..=x as i32 + 1 {
+                if (nx, ny) == (x as i32, y as i32) {
+                    continue;
+                }
+                let cell = cells.get(ny as usize).and_then(|row| row.get(nx as usize));
+                if cell.is_none_or(|cell| cell == &Objects::Wall) {
+                    walls += 1;
+                }
+            }
+        }
+        walls
+    }
+}
+impl Generate for Caves {
+    fn layout(&self, config: &GenConfig, rng: &mut Rng) -> Result<Layout, GenError> {
+        let size = config.size;
+        let cells_size = size / CELL;
+
+        /* ---------------------------------- Noise --------------------------------- */
+        let mut cells = vec![vec![Objects::Wall; cells_size]; cells_size];
+        for row in cells.iter_mut().take(cells_size - 1).skip(1) {
+            for cell in row.iter_mut().take(cells_size - 1).skip(1) {
+                if rng.gen_range(0.0, 1.0) >= self.fill {
+                    *cell = Objects::Air;
+                }
+            }
+        }
+
+        /* -------------------------------- Smoothing ------------------------------- */
+        for _ in 0..self.steps {
+            cells = (0..cells_size)
+                .map(|y| {
+                    (0..cells_size)
+                        .map(|x| {
+                            if x == 0 || y == 0 || x == cells_size - 1 || y == cells_size - 1 {
+                                return Objects::Wall;
+                            }
+                            match Caves::walls(&cells, x, y) {
+                                walls if walls > 4 => Objects::Wall,
+                                walls if walls < 4 => Objects::Air,
+                                _ => cells[y][x],
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+        }
+
+        /* -------------------------------- Upscaling ------------------------------- */
+        let mut open = (0..size)
+            .map(|y| {
+                (0..size)
+                    .map(|x| {
+                        let (cx, cy) = (x / CELL, y / CELL);
+                        if cy < cells_size && cx < cells_size {
+                            cells[cy][cx]
+                        } else {
+                            Objects::Wall
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        keep_largest(&mut open);
+
+        enclose_rooms(open, config, rng)
+    }
+
+    fn empty_between_rooms(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use super::{room_sides, room_target, too_close, Layout};
+use crate::scenes::objects::shapes::rect::Rect;
+use crate::scenes::room_gen::config::GenConfig;
+use crate::scenes::room_gen::error::GenError;
+use crate::scenes::room_gen::gen::Objects;
+use crate::scenes::room_gen::paths::AdjacentRects;
+use crate::scenes::room_gen::rng::Rng;
+use crate::scenes::room_gen::util::draw_rect;
+use crate::scenes::room_gen::validate::interior;
+
+/// Random positions tried for every room
+const PLACE_ATTEMPTS: usize = 30;
+/// Rooms are kept at least this far apart (in tiles) so there is open ground between them
+const ROOM_GAP: f32 = 2.0;
+/// Part of a room's inside that has to be open ground for the room to be placed there
+const MIN_OPEN: f32 = 0.15;
+
+/// Door tile and the side of the room it is on
+type Door = ((usize, usize), (i32, i32));
+
+pub const SIDES: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Tiles of the side of `rect` facing `dir`, without the corners
+fn side_tiles(rect: &Rect, dir: (i32, i32)) -> Vec<(usize, usize)> {
+    let (left, top) = (rect.get_left() as usize, rect.get_top() as usize);
+    let (right, bottom) = (rect.get_right() as usize, rect.get_bottom() as usize);
+    match dir {
+        (1, 0) => (top + 1..bottom).map(|y| (right, y)).collect(),
+        (-1, 0) => (top + 1..bottom).map(|y| (left, y)).collect(),
+        (0, 1) => (left + 1..right).map(|x| (x, bottom)).collect(),
+        _ => (left + 1..right).map(|x| (x, top)).collect(),
+    }
+}
+
+/// Tile next to `tile` in `dir`
+fn step((x, y): (usize, usize), dir: (i32, i32)) -> (usize, usize) {
+    ((x as i32 + dir.0) as usize, (y as i32 + dir.1) as usize)
+}
+
+/// Where the walls of `rect` would cut through open ground, keeping at most `path_size` tiles
+/// in the middle of every opening. Returns the door tiles with the side they are on
+fn find_doors(rect: &Rect, open: &[Vec<Objects>], path_size: usize) -> Vec<Door> {
+    let mut doors = vec![];
+    for dir in SIDES {
+        let mut run = vec![];
+        // `None` at the end closes the last opening
+        for tile in side_tiles(rect, dir).into_iter().map(Some).chain([None]) {
+            let opening = tile.filter(|tile| {
+                let outside = step(*tile, dir);
+                open[tile.1][tile.0] == Objects::Air && open[outside.1][outside.0] == Objects::Air
+            });
+
+            match opening {
+                Some(tile) => run.push(tile),
+                None => {
+                    let kept = run.len().min(path_size);
+                    let start = (run.len() - kept) / 2;
+                    doors.extend(run[start..start + kept].iter().map(|tile| (*tile, dir)));
+                    run.clear();
+                }
+            }
+        }
+    }
+    doors
+}
+
+/// Keeps the biggest area of connected air tiles, everything else is filled with walls
+pub fn keep_largest(open: &mut [Vec<Objects>]) {
+    let mut region = open
+        .iter()
+        .map(|row| vec![None; row.len()])
+        .collect::<Vec<_>>();
+    let mut sizes = vec![];
+
+    for y in 0..open.len() {
+        for x in 0..open[y].len() {
+            if open[y][x] != Objects::Air || region[y][x].is_some() {
+                continue;
+            }
+
+            let id = sizes.len();
+            let mut size = 0;
+            let mut queue = VecDeque::from([(x, y)]);
+            region[y][x] = Some(id);
+            while let Some((x, y)) = queue.pop_front() {
+                size += 1;
+                for (nx, ny) in SIDES.map(|dir| step((x, y), dir)) {
+                    if ny < open.len()
+                        && nx < open[ny].len()
+                        && open[ny][nx] == Objects::Air
+                        && region[ny][nx].is_none()
+                    {
+                        region[ny][nx] = Some(id);
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+    }
+
+    let largest = (0..sizes.len()).max_by_key(|id| sizes[*id]);
+    for (row, regions) in open.iter_mut().zip(region) {
+        for (tile, id) in row.iter_mut().zip(regions) {
+            if id.is_some() && id != largest {
+                *tile = Objects::Wall;
+            }
+        }
+    }
+}
+
+/// Turns walls that only touch other walls into air, nothing can reach them so they are left
+/// empty like the space between rooms of the other generators
+fn hollow(room: &mut [Vec<Objects>]) {
+    let solid = |room: &[Vec<Objects>], x: usize, y: usize| {
+        (y.saturating_sub(1)..=y + 1).all(|ny| {
+            (x.saturating_sub(1)..=x + 1).all(|nx| {
+                room.get(ny)
+                    .and_then(|row| row.get(nx))
+                    .is_none_or(|tile| tile == &Objects::Wall)
+            })
+        })
+    };
+
+    let hollowed = (0..room.len())
+        .flat_map(|y| (0..room[y].len()).map(move |x| (x, y)))
+        .filter(|(x, y)| solid(room, *x, *y))
+        .collect::<Vec<_>>();
+    for (x, y) in hollowed {
+        room[y][x] = Objects::Air;
+    }
+}
+
+/// Grows every room's doors over the open ground between the rooms, two rooms are adjacent when
+/// the ground they grew over meets
+fn adjacents(room: &[Vec<Objects>], rects: &[Rect], rect_doors: &[Vec<Door>]) -> AdjacentRects {
+    let mut owner = room
+        .iter()
+        .map(|row| vec![None; row.len()])
+        .collect::<Vec<_>>();
+    let mut blocked = owner
+        .iter()
+        .map(|row| vec![false; row.len()])
+        .collect::<Vec<_>>();
+    for rect in rects {
+        for row in blocked
+            .iter_mut()
+            .take(rect.get_bottom() as usize + 1)
+            .skip(rect.get_top() as usize)
+        {
+            for tile in row
+                .iter_mut()
+                .take(rect.get_right() as usize + 1)
+                .skip(rect.get_left() as usize)
+            {
+                *tile = true;
+            }
+        }
+    }
+
+    let mut pairs = BTreeSet::new();
+    let mut queue = VecDeque::new();
+    for (index, doors) in rect_doors.iter().enumerate() {
+        for (door, dir) in doors {
+            let (x, y) = step(*door, *dir);
+            match owner[y][x] {
+                None => {
+                    owner[y][x] = Some(index);
+                    queue.push_back((x, y));
+                }
+                Some(other) if other != index => {
+                    pairs.insert((other.min(index), other.max(index)));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    while let Some((x, y)) = queue.pop_front() {
+        let index = owner[y][x].unwrap();
+        for (nx, ny) in SIDES.map(|dir| step((x, y), dir)) {
+            if ny >= room.len() || nx >= room[ny].len() {
+                continue;
+            }
+            if blocked[ny][nx] || room[ny][nx] != Objects::Air {
+                continue;
+            }
+
+            match owner[ny][nx] {
+                None => {
+                    owner[ny][nx] = Some(index);
+                    queue.push_back((nx, ny));
+                }
+                Some(other) if other != index => {
+                    pairs.insert((other.min(index), other.max(index)));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut adjacents = AdjacentRects::new();
+    for (a, b) in pairs {
+        let offset = rects[b].get_center() - rects[a].get_center();
+        let dir = if offset.x.abs() > offset.y.abs() {
+            (offset.x.signum() as i32, 0)
+        } else {
+            (0, offset.y.signum() as i32)
+        };
+        adjacents.entry(a).or_default().push((b, dir));
+    }
+    adjacents
+}
+
+/// Places rooms over the open ground (air) of `open`, walls them in and puts doors wherever their
+/// walls cut through open ground. The open ground is left as it is between the rooms
+pub fn enclose_rooms(
+    open: Vec<Vec<Objects>>,
+    config: &GenConfig,
+    rng: &mut Rng,
+) -> Result<Layout, GenError> {
+    let size = config.size;
+    let (min_side, max_side) = room_sides(config);
+    let target = room_target(config);
+
+    /* ------------------------------ Placing rooms ----------------------------- */
+    let mut rects: Vec<Rect> = vec![];
+    let mut rect_doors = vec![];
+    for _ in 0..target * PLACE_ATTEMPTS {
+        if rects.len() >= target {
+            break;
+        }
+
+        let width = rng.gen_range(min_side, max_side + 1);
+        let height = rng.gen_range(min_side, max_side + 1);
+        if width + 4 >= size || height + 4 >= size {
+            continue;
+        }
+        let x = rng.gen_range(1, size - width - 2);
+        let y = rng.gen_range(1, size - height - 2);
+        let rect = Rect::new(x as f32, y as f32, width as f32, height as f32);
+
+        if rects.iter().any(|r| too_close(r, &rect, ROOM_GAP)) {
+            continue;
+        }
+
+        let inside = interior(&rect).collect::<Vec<_>>();
+        let open_tiles = inside
+            .iter()
+            .filter(|(x, y)| open[*y][*x] == Objects::Air)
+            .count();
+        if (open_tiles as f32) < inside.len() as f32 * MIN_OPEN {
+            continue;
+        }
+
+        let doors = find_doors(&rect, &open, config.path_size);
+        if doors.is_empty() {
+            continue;
+        }
+
+        rects.push(rect);
+        rect_doors.push(doors);
+    }
+
+    /* ------------------------------ Walling rooms ----------------------------- */
+    let mut room = open;
+    let mut doors = vec![];
+    let mut room_doors = vec![vec![]; rects.len()];
+    for (index, rect) in rects.iter().enumerate() {
+        for (x, y) in interior(rect) {
+            room[y][x] = Objects::Air;
+        }
+        draw_rect(rect, &mut room)?;
+
+        for (door, _) in &rect_doors[index] {
+            room_doors[index].push(doors.len());
+            doors.push(*door);
+            room[door.1][door.0] = Objects::Door;
+        }
+    }
+
+    let adjacents = adjacents(&room, &rects, &rect_doors);
+    hollow(&mut room);
+
+    Ok(Layout {
+        room,
+        rects,
+        doors,
+        room_doors,
+        adjacents,
+    })
+}
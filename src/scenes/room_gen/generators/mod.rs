@@ -0,0 +1,303 @@
+pub mod bsp;
+pub mod caves;
+pub mod enclose;
+pub mod stitcher;
+pub mod tunnels;
+
+use enum_dispatch::enum_dispatch;
+
+use self::bsp::Bsp;
+use self::caves::Caves;
+use self::stitcher::Stitcher;
+use self::tunnels::Tunnels;
+use super::config::GenConfig;
+use super::error::GenError;
+use super::gen::Objects;
+use super::paths::AdjacentRects;
+use super::rng::Rng;
+use super::util::set_tile;
+use crate::scenes::objects::shapes::rect::Rect;
+
+/// Rooms, walls, corridors and doors of a floor, before `post` gives the rooms their types
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub room: Vec<Vec<Objects>>,
+    /// Room outlines (in tiles), the walls of every outline are drawn in `room`
+    pub rects: Vec<Rect>,
+    /// Door tiles, set to `Objects::Door` in `room`
+    pub doors: Vec<(usize, usize)>,
+    /// Indexes (in `doors`) of the doors of each room in `rects`
+    pub room_doors: Vec<Vec<usize>>,
+    pub adjacents: AdjacentRects,
+}
+
+#[enum_dispatch]
+pub trait Generate {
+    /// Lays out a floor, the same `config` and `rng` state always yield the same `Layout`
+    fn layout(&self, config: &GenConfig, rng: &mut Rng) -> Result<Layout, GenError>;
+
+    /// Whether the space between rooms is left empty, only then can extra corridors be carved
+    /// through it. Generators leaving open ground between rooms already get loops through it
+    fn empty_between_rooms(&self) -> bool {
+        true
+    }
+}
+
+/// Algorithm used to lay out a floor, every generator gives the same kind of `Layout` so the
+/// rest of the pipeline (prefabs, doors, spawns) doesn't care which one was used
+#[enum_dispatch(Generate)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generator {
+    Bsp(Bsp),
+    Caves(Caves),
+    Tunnels(Tunnels),
+    Stitcher(Stitcher),
+}
+impl Generator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Generator::Bsp(_) => "bsp",
+            Generator::Caves(_) => "caves",
+            Generator::Tunnels(_) => "tunnels",
+            Generator::Stitcher(_) => "stitcher",
+        }
+    }
+}
+
+pub const BSP: Generator = Generator::Bsp(Bsp);
+pub const CAVES: Generator = Generator::Caves(Caves {
+    fill: 0.45,
+    steps: 5,
+});
+pub const TUNNELS: Generator = Generator::Tunnels(Tunnels {
+    coverage: 0.3,
+    width: 3,
+});
+pub const STITCHER: Generator = Generator::Stitcher(Stitcher { corridor: (4, 12) });
+
+pub const GENERATORS: [Generator; 4] = [BSP, CAVES, TUNNELS, STITCHER];
+
+/// Straight corridor from the wall of one room to the wall of another
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Corridor {
+    pub from: usize,
+    pub to: usize,
+    /// Center door tile on the wall of `from`
+    pub start: (i32, i32),
+    /// Center door tile on the wall of `to`
+    pub end: (i32, i32),
+    pub dir: (i32, i32),
+}
+impl Corridor {
+    /// Tiles between the walls of the two rooms, the corridor's walls included
+    pub fn area(&self, path_size: usize) -> Rect {
+        let half_path_size = path_size as i32 / 2 + 1;
+        let (start, end) = (self.start, self.end);
+        let (dx, dy) = (self.dir.0.abs(), self.dir.1.abs());
+        let (left, top) = (start.0.min(end.0) + dx, start.1.min(end.1) + dy);
+        let (right, bottom) = (start.0.max(end.0) - dx, start.1.max(end.1) - dy);
+
+        if dx != 0 {
+            Rect::new(
+                left as f32,
+                (start.1 - half_path_size) as f32,
+                (right - left) as f32,
+                (half_path_size * 2) as f32,
+            )
+        } else {
+            Rect::new(
+                (start.0 - half_path_size) as f32,
+                top as f32,
+                (half_path_size * 2) as f32,
+                (bottom - top) as f32,
+            )
+        }
+    }
+
+    /// Door tiles of the corridor on the wall of one room, `center` being `start` or `end`
+    /// (tiles left of or above the map wrap around to huge indexes, which are out of bounds too)
+    pub fn door_tiles(&self, center: (i32, i32), path_size: usize) -> Vec<(usize, usize)> {
+        let perpendicular = (self.dir.1.abs(), self.dir.0.abs());
+        let half = path_size as i32 / 2;
+        (-half..=half)
+            .map(|i| {
+                (
+                    (center.0 + perpendicular.0 * i) as usize,
+                    (center.1 + perpendicular.1 * i) as usize,
+                )
+            })
+            .collect()
+    }
+
+    /// Draws the walls along both sides of the corridor
+    pub fn carve_walls(&self, room: &mut [Vec<Objects>], path_size: usize) -> Result<(), GenError> {
+        let half_path_size = path_size as i32 / 2 + 1;
+        let perpendicular = (self.dir.1.abs(), self.dir.0.abs());
+
+        // Bounded by the distance between both ends in case `dir` doesn't point from one to the other
+        let length = self.start.0.abs_diff(self.end.0) + self.start.1.abs_diff(self.end.1);
+        let mut pos = self.start;
+        for _ in 0..length {
+            if pos == self.end {
+                break;
+            }
+            for side in [-half_path_size, half_path_size] {
+                let tile = (
+                    pos.0 + perpendicular.0 * side,
+                    pos.1 + perpendicular.1 * side,
+                );
+                set_tile(room, tile, Objects::Wall)?;
+            }
+            pos = (pos.0 + self.dir.0, pos.1 + self.dir.1);
+        }
+        Ok(())
+    }
+
+    /// Draws the corridor, opens its doors on both rooms and connects them
+    pub fn carve(&self, layout: &mut Layout, path_size: usize) -> Result<(), GenError> {
+        for index in [self.from, self.to] {
+            if index >= layout.room_doors.len() {
+                return Err(GenError::MissingRoom(index));
+            }
+        }
+        self.carve_walls(&mut layout.room, path_size)?;
+
+        for (index, center) in [(self.from, self.start), (self.to, self.end)] {
+            for (x, y) in self.door_tiles(center, path_size) {
+                set_tile(&mut layout.room, (x as i32, y as i32), Objects::Door)?;
+                layout.room_doors[index].push(layout.doors.len());
+                layout.doors.push((x, y));
+            }
+        }
+
+        layout
+            .adjacents
+            .entry(self.from)
+            .or_default()
+            .push((self.to, self.dir));
+        Ok(())
+    }
+}
+
+/// Attached room and the center door tiles of the corridor on both rooms
+type Attached = (Rect, (i32, i32), (i32, i32));
+
+/// Tries to put a `width` by `height` room `length` tiles away from the side of `from` facing
+/// `dir`, with enough of both sides facing each other for a corridor to join them
+pub fn attach(
+    from: &Rect,
+    dir: (i32, i32),
+    (width, height): (i32, i32),
+    length: i32,
+    half_path_size: i32,
+    rng: &mut Rng,
+) -> Option<Attached> {
+    let (left, top) = (from.get_left() as i32, from.get_top() as i32);
+    let (right, bottom) = (from.get_right() as i32, from.get_bottom() as i32);
+
+    // The corridor's walls have to land on the walls of both rooms
+    let (side, new_side) = if dir.0 != 0 {
+        ((top, bottom), height)
+    } else {
+        ((left, right), width)
+    };
+    if side.1 - side.0 < half_path_size * 2 || new_side < half_path_size * 2 {
+        return None;
+    }
+    let center = rng.gen_range(side.0 + half_path_size, side.1 - half_path_size + 1);
+    let new_start = center - rng.gen_range(half_path_size, new_side - half_path_size + 1);
+
+    let (rect, start, end) = match dir {
+        (1, 0) => (
+            Rect::new(
+                (right + length) as f32,
+                new_start as f32,
+                width as f32,
+                height as f32,
+            ),
+            (right, center),
+            (right + length, center),
+        ),
+        (-1, 0) => (
+            Rect::new(
+                (left - length - width) as f32,
+                new_start as f32,
+                width as f32,
+                height as f32,
+            ),
+            (left, center),
+            (left - length, center),
+        ),
+        (0, 1) => (
+            Rect::new(
+                new_start as f32,
+                (bottom + length) as f32,
+                width as f32,
+                height as f32,
+            ),
+            (center, bottom),
+            (center, bottom + length),
+        ),
+        _ => (
+            Rect::new(
+                new_start as f32,
+                (top - length - height) as f32,
+                width as f32,
+                height as f32,
+            ),
+            (center, top),
+            (center, top - length),
+        ),
+    };
+    Some((rect, start, end))
+}
+
+/// Smallest and biggest side (in tiles, walls included) of the rooms placed by the generators
+/// that don't split the map
+fn room_sides(config: &GenConfig) -> (usize, usize) {
+    (
+        (config.split_limit / 3).max(8),
+        (config.split_limit * 2 / 3).max(12),
+    )
+}
+
+/// Amount of rooms the generators that don't split the map try to place
+fn room_target(config: &GenConfig) -> usize {
+    (config.size / config.split_limit).pow(2) * 2
+}
+
+/// Whether the two rects (walls included) are closer than `gap` tiles
+fn too_close(a: &Rect, b: &Rect, gap: f32) -> bool {
+    a.get_left() - gap <= b.get_right()
+        && b.get_left() <= a.get_right() + gap
+        && a.get_top() - gap <= b.get_bottom()
+        && b.get_top() <= a.get_bottom() + gap
+}
+
+#[test]
+fn generators() {
+    use super::config::PRESETS;
+    use super::gen::generate_room;
+    use super::post::Room;
+    use super::validate::unreachable_rooms;
+
+    for generator in GENERATORS {
+        for config in PRESETS {
+            let config = GenConfig {
+                generator,
+                ..config
+            };
+            for seed in 0..10 {
+                let floor = generate_room(seed, &config).unwrap();
+                let name = format!("{} {} seed {}", generator.name(), config.name, seed);
+
+                assert!(floor.rects.len() > 2, "{}", name);
+                assert_eq!(unreachable_rooms(&floor), vec![], "{}", name);
+                assert!(floor.room_types.contains(&Room::Start), "{}", name);
+                assert!(floor.stairs.is_some(), "{}", name);
+                assert!(floor.player_spawn.is_some(), "{}", name);
+                assert_eq!(floor, generate_room(seed, &config).unwrap(), "{}", name);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use super::enclose::SIDES;
+use super::{attach, room_sides, room_target, too_close, Corridor, Generate, Layout};
+use crate::scenes::objects::shapes::rect::Rect;
+use crate::scenes::room_gen::config::GenConfig;
+use crate::scenes::room_gen::error::GenError;
+use crate::scenes::room_gen::gen::Objects;
+use crate::scenes::room_gen::paths::AdjacentRects;
+use crate::scenes::room_gen::rng::Rng;
+use crate::scenes::room_gen::util::draw_rect;
+
+/// Room shapes, as ratios of the biggest room side
+const TEMPLATES: [(f32, f32); 5] = [(1.0, 1.0), (1.0, 0.6), (0.6, 1.0), (0.6, 0.6), (0.8, 0.8)];
+/// Tries at attaching a room for every room placed
+const PLACE_ATTEMPTS: usize = 30;
+
+/// Room graph stitcher, grows a tree of rooms by attaching room templates to the sides of the
+/// rooms already placed with straight corridors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stitcher {
+    /// Shortest and longest corridor between two rooms (in tiles)
+    pub corridor: (usize, usize),
+}
+impl Generate for Stitcher {
+    fn layout(&self, config: &GenConfig, rng: &mut Rng) -> Result<Layout, GenError> {
+        let size = config.size;
+        let half_path_size = config.path_size as i32 / 2 + 1;
+        let (min_side, max_side) = room_sides(config);
+        let target = room_target(config);
+
+        let template = |rng: &mut Rng| {
+            let (width, height) = *rng.choose(&TEMPLATES);
+            (
+                ((max_side as f32 * width) as usize).max(min_side) as i32,
+                ((max_side as f32 * height) as usize).max(min_side) as i32,
+            )
+        };
+
+        /* ------------------------------- First room ------------------------------- */
+        let (width, height) = template(rng);
+        let mut rects = vec![Rect::new(
+            ((size as i32 - width) / 2) as f32,
+            ((size as i32 - height) / 2) as f32,
+            width as f32,
+            height as f32,
+        )];
+
+        /* ------------------------------ Attaching rooms ----------------------------- */
+        let mut corridors: Vec<Corridor> = vec![];
+        let mut corridor_rects: Vec<Rect> = vec![];
+        for _ in 0..target * PLACE_ATTEMPTS {
+            if rects.len() >= target {
+                break;
+            }
+
+            let from = rng.gen_range(0, rects.len());
+            let dir = *rng.choose(&SIDES);
+            let size_wanted = template(rng);
+            let length = rng.gen_range(self.corridor.0, self.corridor.1 + 1) as i32;
+            let (rect, start, end) =
+                match attach(&rects[from], dir, size_wanted, length, half_path_size, rng) {
+                    Some(attached) => attached,
+                    None => continue,
+                };
+
+            if rect.get_left() < 1.0
+                || rect.get_top() < 1.0
+                || rect.get_right() > size as f32 - 2.0
+                || rect.get_bottom() > size as f32 - 2.0
+            {
+                continue;
+            }
+
+            let corridor = Corridor {
+                from,
+                to: rects.len(),
+                start,
+                end,
+                dir,
+            };
+            let corridor_rect = corridor.area(config.path_size);
+
+            let blocked = rects.iter().any(|r| too_close(r, &rect, 1.0))
+                || corridor_rects.iter().any(|r| too_close(r, &rect, 1.0))
+                || rects.iter().any(|r| too_close(r, &corridor_rect, 0.0))
+                || corridor_rects
+                    .iter()
+                    .any(|r| too_close(r, &corridor_rect, 1.0));
+            if blocked {
+                continue;
+            }
+
+            corridors.push(corridor);
+            corridor_rects.push(corridor_rect);
+            rects.push(rect);
+        }
+
+        /* --------------------------------- Drawing -------------------------------- */
+        let mut room = vec![vec![Objects::Air; size]; size];
+        for rect in &rects {
+            draw_rect(rect, &mut room)?;
+        }
+
+        let mut layout = Layout {
+            room,
+            room_doors: vec![vec![]; rects.len()],
+            rects,
+            doors: vec![],
+            adjacents: AdjacentRects::new(),
+        };
+        for corridor in &corridors {
+            corridor.carve(&mut layout, config.path_size)?;
+        }
+        Ok(layout)
+    }
+}
@@ -0,0 +1,61 @@
+use super::enclose::{enclose_rooms, SIDES};
+use super::{Generate, Layout};
+use crate::scenes::room_gen::config::GenConfig;
+use crate::scenes::room_gen::error::GenError;
+use crate::scenes::room_gen::gen::Objects;
+use crate::scenes::room_gen::rng::Rng;
+
+/// Chance of the digger changing direction after every step
+const TURN_CHANCE: f32 = 0.2;
+/// Tiles kept as wall around the edges of the map
+const MARGIN: usize = 2;
+
+/// Drunkard's walk tunnels, a digger wanders from the middle of the map until enough of it is
+/// dug out and rooms are walled in along the tunnels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tunnels {
+    /// Part (`0.0` - `1.0`) of the map dug out
+    pub coverage: f32,
+    /// Width of the tunnels (in tiles)
+    pub width: usize,
+}
+impl Generate for Tunnels {
+    fn layout(&self, config: &GenConfig, rng: &mut Rng) -> Result<Layout, GenError> {
+        let size = config.size;
+        let mut open = vec![vec![Objects::Wall; size]; size];
+
+        let target = (self.coverage * (size * size) as f32) as usize;
+        let max = size.saturating_sub(MARGIN + self.width).max(MARGIN);
+        let (mut x, mut y) = (size / 2, size / 2);
+        let mut dir = *rng.choose(&SIDES);
+        let mut dug = 0;
+
+        // The digger often walks back over its own tunnels, the bound keeps it from wandering forever
+        for _ in 0..size * size {
+            if dug >= target {
+                break;
+            }
+
+            for row in open.iter_mut().skip(y).take(self.width) {
+                for tile in row.iter_mut().skip(x).take(self.width) {
+                    if tile == &Objects::Wall {
+                        *tile = Objects::Air;
+                        dug += 1;
+                    }
+                }
+            }
+
+            if rng.gen_range(0.0, 1.0) < TURN_CHANCE {
+                dir = *rng.choose(&SIDES);
+            }
+            x = (x as i32 + dir.0).clamp(MARGIN as i32, max as i32) as usize;
+            y = (y as i32 + dir.1).clamp(MARGIN as i32, max as i32) as usize;
+        }
+
+        enclose_rooms(open, config, rng)
+    }
+
+    fn empty_between_rooms(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+
+use super::paths::AdjacentRects;
+
+/// Which rooms (indexes in `Floor::rects`) are joined by a corridor or doorway
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RoomGraph {
+    neighbours: Vec<Vec<usize>>,
+}
+impl RoomGraph {
+    /// Turns the one-way connections made by the generators into a graph of `len` rooms
+    pub fn new(adjacents: &AdjacentRects, len: usize) -> RoomGraph {
+        let mut neighbours = vec![vec![]; len];
+        for (rect_index, rs) in adjacents {
+            for (r_index, _) in rs {
+                if !neighbours[*rect_index].contains(r_index) {
+                    neighbours[*rect_index].push(*r_index);
+                    neighbours[*r_index].push(*rect_index);
+                }
+            }
+        }
+        RoomGraph { neighbours }
+    }
+
+    pub fn len(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbours.is_empty()
+    }
+
+    pub fn neighbours(&self, room: usize) -> &[usize] {
+        &self.neighbours[room]
+    }
+
+    /// Joins two rooms, for rooms added after the layout was made
+    pub fn connect(&mut self, a: usize, b: usize) {
+        let len = self.len().max(a + 1).max(b + 1);
+        self.neighbours.resize(len, vec![]);
+        if !self.neighbours[a].contains(&b) {
+            self.neighbours[a].push(b);
+            self.neighbours[b].push(a);
+        }
+    }
+
+    /// Breadth first search from `from`, returns the room each room was reached from
+    fn parents(&self, from: usize) -> Vec<Option<usize>> {
+        let mut parents = vec![None; self.len()];
+        parents[from] = Some(from);
+
+        let mut queue = VecDeque::from([from]);
+        while let Some(room) = queue.pop_front() {
+            for neighbour in &self.neighbours[room] {
+                if parents[*neighbour].is_none() {
+                    parents[*neighbour] = Some(room);
+                    queue.push_back(*neighbour);
+                }
+            }
+        }
+        parents
+    }
+
+    /// Amount of rooms between `from` and every other room, `None` if unreachable
+    pub fn distances(&self, from: usize) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.len()];
+        distances[from] = Some(0);
+
+        let mut queue = VecDeque::from([from]);
+        while let Some(room) = queue.pop_front() {
+            let distance = distances[room].unwrap();
+            for neighbour in &self.neighbours[room] {
+                if distances[*neighbour].is_none() {
+                    distances[*neighbour] = Some(distance + 1);
+                    queue.push_back(*neighbour);
+                }
+            }
+        }
+        distances
+    }
+
+    pub fn distance(&self, from: usize, to: usize) -> Option<usize> {
+        self.distances(from)[to]
+    }
+
+    /// Reachable room farthest from `from`, the lowest index wins ties
+    pub fn farthest(&self, from: usize) -> usize {
+        let distances = self.distances(from);
+        let mut farthest = from;
+        for (room, distance) in distances.iter().enumerate() {
+            if distance > &distances[farthest] {
+                farthest = room;
+            }
+        }
+        farthest
+    }
+
+    /// Shortest list of rooms going from `from` to `to`, both included
+    pub fn route(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let parents = self.parents(from);
+        parents[to]?;
+
+        let mut route = vec![to];
+        let mut room = to;
+        while room != from {
+            room = parents[room].unwrap();
+            route.push(room);
+        }
+        route.reverse();
+        Some(route)
+    }
+
+    /// Rooms with a single way in
+    pub fn dead_ends(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|room| self.neighbours[*room].len() == 1)
+            .collect()
+    }
+
+    /// Rooms that have to be walked through to get from `start` to `end`: the rooms of the
+    /// shortest route without a way around them
+    pub fn critical_path(&self, start: usize, end: usize) -> Vec<usize> {
+        let route = match self.route(start, end) {
+            Some(route) => route,
+            None => return vec![],
+        };
+
+        route
+            .iter()
+            .copied()
+            .filter(|room| {
+                *room == start || *room == end || {
+                    let without = RoomGraph {
+                        neighbours: self
+                            .neighbours
+                            .iter()
+                            .enumerate()
+                            .map(|(index, rs)| {
+                                if index == *room {
+                                    vec![]
+                                } else {
+                                    rs.iter().copied().filter(|r| r != room).collect()
+                                }
+                            })
+                            .collect(),
+                    };
+                    without.distance(start, end).is_none()
+                }
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn graph() {
+    use maplit::btreemap;
+
+    //  0 - 1 - 2 - 3 - 6
+    //      |   |
+    //      4 - 5
+    let adjacents = btreemap! {
+        0 => vec![(1, (1, 0))],
+        1 => vec![(2, (1, 0)), (4, (0, 1))],
+        2 => vec![(3, (1, 0)), (5, (0, 1))],
+        3 => vec![(6, (1, 0))],
+        4 => vec![(5, (1, 0))],
+    };
+    let graph = RoomGraph::new(&adjacents, 8);
+
+    assert_eq!(graph.neighbours(1), &[0, 2, 4]);
+    assert_eq!(graph.distance(0, 6), Some(4));
+    assert_eq!(graph.distance(0, 7), None);
+    assert_eq!(graph.farthest(0), 6);
+    assert_eq!(graph.route(0, 6), Some(vec![0, 1, 2, 3, 6]));
+    assert_eq!(graph.route(0, 7), None);
+    assert_eq!(graph.dead_ends(), vec![0, 6]);
+    assert_eq!(graph.critical_path(0, 6), vec![0, 1, 2, 3, 6]);
+    assert_eq!(graph.critical_path(4, 6), vec![4, 2, 3, 6]);
+
+    let mut graph = graph;
+    graph.connect(0, 8);
+    assert_eq!(graph.len(), 9);
+    assert_eq!(graph.neighbours(8), &[0]);
+    assert_eq!(graph.distance(6, 8), Some(5));
+    assert_eq!(graph.dead_ends(), vec![6, 8]);
+}
@@ -1,14 +1,15 @@
-use macroquad::rand::gen_range;
-
+use super::config::GenConfig;
 use super::gen::{Direction, Objects, SplitQueue};
+use super::rng::Rng;
 use super::util::pop_random;
 use crate::tuple_abs_diff;
 
-pub fn init_rects(size: usize, split_limit: usize) -> Vec<Vec<Objects>> {
+pub fn init_rects(config: &GenConfig, rng: &mut Rng) -> Vec<Vec<Objects>> {
+    let size = config.size;
     let mut room = vec![vec![Objects::Air; size]; size];
 
     let mut queue = vec![SplitQueue::new(
-        if gen_range(0, 2) == 0 {
+        if rng.gen_range(0, 2) == 0 {
             Direction::Vertical
         } else {
             Direction::Horizontal
@@ -17,19 +18,24 @@ pub fn init_rects(size: usize, split_limit: usize) -> Vec<Vec<Objects>> {
         (0, size - 1),
     )];
 
-    while let Some(split) = pop_random(&mut queue) {
+    while let Some(split) = pop_random(&mut queue, rng) {
         let (major_limit, minor_limit) = match split.direction {
             Direction::Vertical => (split.x_limits, split.y_limits),
             Direction::Horizontal => (split.y_limits, split.x_limits),
         };
 
-        if tuple_abs_diff!(major_limit) <= split_limit {
+        if tuple_abs_diff!(major_limit) <= config.split_limit {
             continue;
         }
 
         /* ---------------------------- Splitting parent ---------------------------- */
-        let diff = tuple_abs_diff!(major_limit) as f32 * gen_range(0.3, 0.7);
+        let diff = tuple_abs_diff!(major_limit) as f32
+            * rng.gen_range(config.split_ratio.0, config.split_ratio.1);
         let rand_split = major_limit.0 + diff as usize;
+        // Both sides need at least a tile
+        if rand_split <= major_limit.0 || rand_split >= major_limit.1 {
+            continue;
+        }
         for i in minor_limit.0..=minor_limit.1 {
             match split.direction {
                 Direction::Vertical => {
@@ -0,0 +1,163 @@
+use super::config::GenConfig;
+use super::error::GenError;
+use super::gen::Objects;
+use super::generators::{Corridor, Layout};
+use super::rng::Rng;
+use super::util::get_tile;
+use super::validate::rect_tiles;
+
+/// Longest extra corridor (in tiles), only rooms closer than this get joined
+const MAX_LOOP_LENGTH: i32 = 20;
+
+/// Whether a corridor already goes from `a` to `b` or from `b` to `a`
+fn joined(layout: &Layout, a: usize, b: usize) -> bool {
+    let joins = |from: usize, to: usize| {
+        layout
+            .adjacents
+            .get(&from)
+            .is_some_and(|rs| rs.iter().any(|(r, _)| *r == to))
+    };
+    joins(a, b) || joins(b, a)
+}
+
+/// Straight corridor between the sides of `from` and `to` facing each other, `None` when the
+/// sides don't overlap enough to fit one or are too far apart
+fn facing(
+    layout: &Layout,
+    (from, to): (usize, usize),
+    half_path_size: i32,
+    rng: &mut Rng,
+) -> Option<Corridor> {
+    let (a, b) = (&layout.rects[from], &layout.rects[to]);
+    let [a_left, a_top, a_right, a_bottom] =
+        [a.get_left(), a.get_top(), a.get_right(), a.get_bottom()].map(|side| side as i32);
+    let [b_left, b_top, b_right, b_bottom] =
+        [b.get_left(), b.get_top(), b.get_right(), b.get_bottom()].map(|side| side as i32);
+
+    // Position of the walls the corridor goes between, the direction it goes in and the range
+    // its walls have to stay in to land on the walls of both rooms
+    let (start, end, dir, range) = if a_right < b_left {
+        (
+            a_right,
+            b_left,
+            (1, 0),
+            (a_top.max(b_top), a_bottom.min(b_bottom)),
+        )
+    } else if b_right < a_left {
+        (
+            a_left,
+            b_right,
+            (-1, 0),
+            (a_top.max(b_top), a_bottom.min(b_bottom)),
+        )
+    } else if a_bottom < b_top {
+        (
+            a_bottom,
+            b_top,
+            (0, 1),
+            (a_left.max(b_left), a_right.min(b_right)),
+        )
+    } else if b_bottom < a_top {
+        (
+            a_top,
+            b_bottom,
+            (0, -1),
+            (a_left.max(b_left), a_right.min(b_right)),
+        )
+    } else {
+        return None;
+    };
+
+    let length = start.abs_diff(end) as i32;
+    if !(2..=MAX_LOOP_LENGTH).contains(&length) || range.1 - range.0 < half_path_size * 2 {
+        return None;
+    }
+
+    let center = rng.gen_range(range.0 + half_path_size, range.1 - half_path_size + 1);
+    let (start, end) = if dir.0 != 0 {
+        ((start, center), (end, center))
+    } else {
+        ((center, start), (center, end))
+    };
+    Some(Corridor {
+        from,
+        to,
+        start,
+        end,
+        dir,
+    })
+}
+
+/// Whether the corridor only goes over empty space and its doors only cut through walls
+fn clear(layout: &Layout, corridor: &Corridor, path_size: usize) -> bool {
+    let empty =
+        rect_tiles(&corridor.area(path_size)).all(|(x, y)| layout.room[y][x] == Objects::Air);
+    let walls = [corridor.start, corridor.end]
+        .into_iter()
+        .flat_map(|center| corridor.door_tiles(center, path_size))
+        .all(|(x, y)| layout.room[y][x] == Objects::Wall);
+    empty && walls
+}
+
+/// Joins rooms close to each other that aren't connected yet with extra corridors, so the layout
+/// has loops instead of only being a tree
+pub fn add_loops(layout: &mut Layout, config: &GenConfig, rng: &mut Rng) -> Result<(), GenError> {
+    let half_path_size = config.path_size as i32 / 2 + 1;
+
+    for from in 0..layout.rects.len() {
+        for to in from + 1..layout.rects.len() {
+            if joined(layout, from, to) {
+                continue;
+            }
+
+            let corridor = match facing(layout, (from, to), half_path_size, rng) {
+                Some(corridor) => corridor,
+                None => continue,
+            };
+            if clear(layout, &corridor, config.path_size)
+                && rng.gen_range(0.0, 1.0) < config.loop_chance
+            {
+                corridor.carve(layout, config.path_size)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn loops() {
+    use super::config::PRESETS;
+    use super::gen::generate_room;
+    use super::generators::STITCHER;
+
+    let count = |config: &GenConfig, seed: u64| {
+        let floor = generate_room(seed, config).unwrap();
+        let edges = (0..floor.graph.len())
+            .map(|room| floor.graph.neighbours(room).len())
+            .sum::<usize>()
+            / 2;
+        (edges, floor.rects.len())
+    };
+
+    for config in PRESETS {
+        let tree = GenConfig {
+            generator: STITCHER,
+            loop_chance: 0.0,
+            ..config
+        };
+        let loops = GenConfig {
+            loop_chance: 1.0,
+            ..tree
+        };
+
+        let mut extra = 0;
+        for seed in 0..5 {
+            let (edges, rooms) = count(&tree, seed);
+            assert_eq!(edges, rooms - 1, "{} seed {}", config.name, seed);
+
+            let (edges, rooms) = count(&loops, seed);
+            extra += edges + 1 - rooms;
+        }
+        assert!(extra > 0, "{}", config.name);
+    }
+}
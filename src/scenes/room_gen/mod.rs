@@ -1,5 +1,16 @@
+pub mod config;
+pub mod error;
+pub mod export;
 pub mod gen;
+pub mod generators;
+pub mod graph;
 pub mod init;
+pub mod loops;
 pub mod paths;
 pub mod post;
+pub mod prefabs;
+pub mod rng;
+pub mod secrets;
+pub mod spawns;
 pub mod util;
+pub mod validate;
@@ -1,224 +1,151 @@
-use std::collections::hash_map::Entry::Vacant;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use macroquad::prelude::vec2;
-use maplit::hashmap;
 
+use super::config::GenConfig;
+use super::error::GenError;
 use super::gen::Objects;
+use super::generators::enclose::SIDES;
+use super::generators::{Corridor, Layout};
+use super::util::get_tile;
 use crate::scenes::objects::shapes::rect::Rect;
 
-type AdjacentRects = HashMap<usize, Vec<(usize, (i32, i32))>>;
-fn adjacent_rects(rects: &[Rect], size: usize, room: &mut [Vec<Objects>]) -> AdjacentRects {
-    // TODO make constant variables such as size, path_size, etc constants in util.rs
-    let path_size = size / 60;
-    let half_path_size = path_size / 2 + 1;
+/// Rect index to `(connected rect index, direction)`, each connection is only stored once.
+/// Ordered so corridors are always carved in the same order for a given seed
+pub type AdjacentRects = BTreeMap<usize, Vec<(usize, (i32, i32))>>;
 
-    let mut adjacent_rects: AdjacentRects = hashmap! {};
+/// Casts a ray from the center of every rect in each direction, the first other rect it hits is
+/// adjacent unless a corridor is in the way
+fn adjacent_rects(rects: &[Rect], config: &GenConfig, room: &[Vec<Objects>]) -> AdjacentRects {
+    let size = config.size as i32;
+    let half_path_size = (config.path_size / 2 + 1) as i32;
+
+    let mut adjacent_rects = AdjacentRects::new();
     for (rect_index, rect) in rects.iter().enumerate() {
+        let center = (rect.get_center().x as i32, rect.get_center().y as i32);
         let mut already_adjacent = vec![];
-        for dir in &[(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        for dir in SIDES {
+            let perpendicular = (dir.1.abs(), dir.0.abs());
             for i in 0..size / 2 {
-                let x = if dir.0 != 0 {
-                    rect.get_center().x as i32 + dir.0 * i as i32
-                } else {
-                    rect.get_center().x as i32
-                };
-
-                let y = if dir.1 != 0 {
-                    rect.get_center().y as i32 + dir.1 * i as i32
-                } else {
-                    rect.get_center().y as i32
-                };
-
-                if x < 0 || y < 0 || x >= size as i32 || y >= size as i32 {
+                let (x, y) = (center.0 + dir.0 * i, center.1 + dir.1 * i);
+                if x < 0 || y < 0 || x >= size || y >= size {
                     break;
                 }
 
-                let r = match rects
+                let r_index = match rects
                     .iter()
                     .position(|r| r.touches_point(&vec2(x as f32, y as f32)))
                 {
-                    Some(rect) => rects[rect],
+                    Some(r_index) => r_index,
                     None => {
-                        match dir {
-                            (1, 0) | (-1, 0) => {
-                                if room[y as usize - half_path_size][x as usize] == Objects::Wall
-                                    || room[y as usize + half_path_size][x as usize]
-                                        == Objects::Wall
-                                {
-                                    break;
-                                }
-                            }
-                            (0, 1) | (0, -1) => {
-                                if room[y as usize][x as usize - half_path_size] == Objects::Wall
-                                    || room[y as usize][x as usize + half_path_size]
-                                        == Objects::Wall
-                                {
-                                    break;
-                                }
-                            }
-                            _ => panic!(),
-                        };
-
+                        // A corridor would run into walls here, outside of the map counts as wall
+                        let blocked = [-half_path_size, half_path_size].iter().any(|side| {
+                            let tile = (x + perpendicular.0 * side, y + perpendicular.1 * side);
+                            get_tile(room, tile).is_none_or(|tile| tile == Objects::Wall)
+                        });
+                        if blocked {
+                            break;
+                        }
                         continue;
                     }
                 };
 
-                if rect != &r && rects.contains(&r) && !already_adjacent.contains(&r) {
-                    already_adjacent.push(r);
-                    let r_index = rects.iter().position(|rect| rect == &r).unwrap();
+                if r_index != rect_index && !already_adjacent.contains(&r_index) {
+                    already_adjacent.push(r_index);
 
                     // Check if the rect-r connection already exists
-                    if let Some(vec) = adjacent_rects.get(&r_index) {
-                        if vec.iter().any(|x| x.0 == rect_index) {
-                            break;
-                        }
-                    }
-
-                    if let Vacant(entry) = adjacent_rects.entry(rect_index) {
-                        entry.insert(vec![(r_index, *dir)]);
-                    } else {
+                    let exists = adjacent_rects
+                        .get(&r_index)
+                        .is_some_and(|rs| rs.iter().any(|(r, _)| *r == rect_index));
+                    if !exists {
                         adjacent_rects
-                            .get_mut(&rect_index)
-                            .unwrap()
-                            .push((r_index, *dir));
-                    };
-
+                            .entry(rect_index)
+                            .or_default()
+                            .push((r_index, dir));
+                    }
                     break;
                 }
             }
         }
-        // break;
     }
 
     adjacent_rects
 }
 
-pub fn paths(rects: &[Rect], size: usize, room: &mut [Vec<Objects>]) -> Vec<(usize, usize)> {
-    let path_size = size / 60;
-    let half_path_size = path_size / 2 + 1;
+/// Straight corridor from `rects[from]` to `rects[to]` going in `dir`, as close to the center of
+/// `from` as it can be with its walls landing on the walls of both rects. `None` when the rects
+/// don't face each other enough for that
+fn corridor(
+    rects: &[Rect],
+    (from, to): (usize, usize),
+    dir: (i32, i32),
+    half_path_size: i32,
+) -> Option<Corridor> {
+    let (rect, r) = (rects.get(from)?, rects.get(to)?);
+
+    // Walls the corridor goes between
+    let (start, end) = match dir {
+        (0, 1) => (rect.get_bottom(), r.get_top()),
+        (0, -1) => (rect.get_top(), r.get_bottom()),
+        (1, 0) => (rect.get_right(), r.get_left()),
+        _ => (rect.get_left(), r.get_right()),
+    };
+
+    // Range the corridor's walls have to stay in
+    let (low, high, middle) = if dir.0 != 0 {
+        (
+            rect.get_top().max(r.get_top()),
+            rect.get_bottom().min(r.get_bottom()),
+            rect.get_center().y,
+        )
+    } else {
+        (
+            rect.get_left().max(r.get_left()),
+            rect.get_right().min(r.get_right()),
+            rect.get_center().x,
+        )
+    };
+    let (low, high) = (low as i32 + half_path_size, high as i32 - half_path_size);
+    if low > high {
+        return None;
+    }
+    let middle = (middle as i32).clamp(low, high);
+
+    let (start, end) = if dir.0 != 0 {
+        ((start as i32, middle), (end as i32, middle))
+    } else {
+        ((middle, start as i32), (middle, end as i32))
+    };
+    Some(Corridor {
+        from,
+        to,
+        start,
+        end,
+        dir,
+    })
+}
+
+/// Carves corridors between adjacent rects and opens up their doors, rects that don't face each
+/// other enough for a straight corridor are left unconnected
+pub fn paths(layout: &mut Layout, config: &GenConfig) -> Result<(), GenError> {
+    let half_path_size = (config.path_size / 2 + 1) as i32;
 
-    let mut doors = vec![];
-    let adjacents = adjacent_rects(rects, size, room);
+    let adjacents = adjacent_rects(&layout.rects, config, &layout.room);
     for (rect_index, rs) in &adjacents {
-        let rect = rects[*rect_index];
         for (r_index, dir) in rs {
-            let r = rects[*r_index];
-
-            /* -------------------------- Getting start and end ------------------------- */
-            let mut start = match dir {
-                (0, 1) => (rect.get_center().x as i32, rect.get_bottom() as i32),
-                (0, -1) => (rect.get_center().x as i32, rect.get_top() as i32),
-                (1, 0) => (rect.get_right() as i32, rect.get_center().y as i32),
-                (-1, 0) => (rect.get_left() as i32, rect.get_center().y as i32),
-                _ => panic!(),
-            };
-            let mut end = match dir {
-                (0, -1) => (rect.get_center().x as i32, r.get_bottom() as i32),
-                (0, 1) => (rect.get_center().x as i32, r.get_top() as i32),
-                (-1, 0) => (r.get_right() as i32, rect.get_center().y as i32),
-                (1, 0) => (r.get_left() as i32, rect.get_center().y as i32),
-                _ => panic!(),
-            };
-
-            /* ----------------------- Fix paths going out of rect ---------------------- */
-            match dir {
-                (0, 1) | (0, -1) => {
-                    let touches = |start: (i32, i32), end: (i32, i32)| {
-                        (
-                            r.touches_point(&vec2(
-                                start.0 as f32 - half_path_size as f32,
-                                end.1 as f32,
-                            )),
-                            r.touches_point(&vec2(
-                                start.0 as f32 + half_path_size as f32,
-                                end.1 as f32,
-                            )),
-                        )
-                    };
-
-                    let (mut l_touches, mut r_touches) = touches(start, end);
-
-                    while !l_touches {
-                        start.0 += 1;
-                        end.0 += 1;
-                        (l_touches, _) = touches(start, end);
-                    }
-
-                    while !r_touches {
-                        start.0 -= 1;
-                        end.0 -= 1;
-                        (_, r_touches) = touches(start, end);
-                    }
-                }
-                (1, 0) | (-1, 0) => {
-                    let touches = |start: (i32, i32), end: (i32, i32)| {
-                        (
-                            r.touches_point(&vec2(
-                                end.0 as f32,
-                                start.1 as f32 - half_path_size as f32,
-                            )),
-                            r.touches_point(&vec2(
-                                end.0 as f32,
-                                start.1 as f32 + half_path_size as f32,
-                            )),
-                        )
-                    };
-
-                    let (mut t_touches, mut b_touches) = touches(start, end);
-
-                    while !t_touches {
-                        start.1 += 1;
-                        end.1 += 1;
-                        (t_touches, _) = touches(start, end);
-                    }
-
-                    while !b_touches {
-                        start.1 -= 1;
-                        end.1 -= 1;
-                        (_, b_touches) = touches(start, end);
-                    }
-                }
-                _ => panic!(),
-            };
-
-            /* ---------------------------- Drawing corridors --------------------------- */
-            let mut pos = start;
-            loop {
-                if pos == end || pos.0 < 1 || pos.1 < 1 {
-                    break;
-                }
-
-                if dir.0 == 0 {
-                    room[pos.1 as usize][pos.0 as usize + half_path_size] = Objects::Wall;
-                    room[pos.1 as usize][pos.0 as usize - half_path_size] = Objects::Wall;
-                } else {
-                    room[pos.1 as usize + half_path_size][pos.0 as usize] = Objects::Wall;
-                    room[pos.1 as usize - half_path_size][pos.0 as usize] = Objects::Wall;
-                }
-
-                pos.0 += dir.0;
-                pos.1 += dir.1;
-            }
-
-            /* ------------------------------ Getting doors ----------------------------- */
-            match dir {
-                (1, 0) | (-1, 0) => {
-                    for i in 0..path_size {
-                        doors.push((end.0 as usize, end.1 as usize - path_size / 2 + i));
-                        doors.push((start.0 as usize, start.1 as usize - path_size / 2 + i));
-                    }
-                }
-                (0, 1) | (0, -1) => {
-                    for i in 0..path_size {
-                        doors.push((end.0 as usize - path_size / 2 + i, end.1 as usize));
-                        doors.push((start.0 as usize - path_size / 2 + i, start.1 as usize));
-                    }
-                }
-                _ => panic!(),
+            if let Some(corridor) =
+                corridor(&layout.rects, (*rect_index, *r_index), *dir, half_path_size)
+            {
+                corridor.carve(layout, config.path_size)?;
             }
         }
     }
 
-    doors
+    /* ------------------------------ Opening doors ----------------------------- */
+    // The walls of later corridors can be drawn over the doors of earlier ones
+    for (x, y) in &layout.doors {
+        layout.room[*y][*x] = Objects::Door;
+    }
+
+    Ok(())
 }
@@ -1,136 +1,92 @@
-use lazy_static::lazy_static;
-use macroquad::rand::gen_range;
-
+use super::config::GenConfig;
 use super::gen::Objects;
+use super::graph::RoomGraph;
+use super::prefabs::place_prefabs;
+use super::rng::Rng;
 use crate::scenes::objects::shapes::rect::Rect;
-use crate::scenes::room_gen::util::{print_room, rotate_array};
-use crate::util::random_array;
 
-macro_rules! objects {
-    ($size: expr, $( $object: expr ), *) => {
-		[
-			$(
-				{
-					let mut vec = vec![vec![Objects::Air; $size]; $size];
-					for (y, line) in $object.lines().enumerate() {
-						for (x, char) in line.trim().chars().enumerate() {
-							vec[y][x] = if char == '#' {
-								Objects::Wall
-							} else {
-								Objects::Air
-							};
-						}
-					}
-					vec
-				},
-			)*
-		]
-    };
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Room {
+    /// Where the player spawns, the farthest room from the end
+    Start,
+    /// Has the stairs to the next floor
+    End,
+    Battle,
+    /// Dead ends off the main route
+    Treasure,
+    Shop,
+    /// Hidden behind a destructible wall of a dead end
+    Secret,
 }
 
-lazy_static! {
-    static ref SMALL_OBJECTS: [Vec<Vec<Objects>>; 4] = objects!(
-        2,
-        "##
-         ##",
-        "#
-         ##",
-        "##",
-        "#"
-    );
-    static ref MEDIUM_OBJECTS: [Vec<Vec<Objects>>; 7] = objects!(
-        3,
-        "###
-         ###",
-        "##
-         ###",
-        "##
-         0##",
-        "###
-         # #",
-        "###
-         ##
-         #  ",
-        "0##
-         0##
-         ## ",
-        "###
-         # #
-         ###"
-    );
-    static ref LARGE_OBJECTS: [Vec<Vec<Objects>>; 4] = objects!(
-        5,
-        "##
-        
-            ##
-         #   #
-         ##  #",
-        "##  #
-         0000#
-        
-         #  ##
-         #   #",
-        "##
-         #   #
-             #
-          
-         ##   ",
-        "#  ##
-         #
-         0000#
-         000##
-         #    "
-    );
-}
+/// Picks a type for every rect: start and end are the two rooms farthest apart, dead ends become
+/// treasure rooms, one of the rest becomes a shop and everything else is a battle room
+pub fn classify(graph: &RoomGraph, rng: &mut Rng) -> Vec<Room> {
+    let mut types = vec![Room::Battle; graph.len()];
+    if graph.is_empty() {
+        return types;
+    }
+
+    /* ------------------------------- Start / end ------------------------------ */
+    let end = graph.farthest(0);
+    let start = graph.farthest(end);
+    types[end] = Room::End;
+    types[start] = Room::Start;
+
+    /* -------------------------------- Treasure -------------------------------- */
+    for index in graph.dead_ends() {
+        if index != start && index != end {
+            types[index] = Room::Treasure;
+        }
+    }
 
-fn complete_random(array: &[Vec<Vec<Objects>>]) -> Vec<Vec<Objects>> {
-    let mut random = random_array(array).to_vec();
-    for _ in 0..gen_range(0, 3) {
-        rotate_array(&mut random);
+    /* ---------------------------------- Shop ---------------------------------- */
+    let battles = (0..graph.len())
+        .filter(|index| types[*index] == Room::Battle)
+        .collect::<Vec<_>>();
+    if battles.len() > 2 {
+        types[*rng.choose(&battles)] = Room::Shop;
     }
-    random
+
+    types
 }
 
-fn paste_object(
-    x: usize,
-    y: usize,
-    object: &[Vec<Objects>],
-    rect: &Rect,
+/// Classifies the rooms and fills them with prefab obstacles
+pub fn post(
     room: &mut [Vec<Objects>],
-) {
-    let x_offset = rect.pos.x as usize;
-    let y_offset = rect.pos.y as usize;
+    rects: &mut [Rect],
+    graph: &RoomGraph,
+    (doors, room_doors): (&[(usize, usize)], &[Vec<usize>]),
+    config: &GenConfig,
+    rng: &mut Rng,
+) -> Vec<Room> {
+    let types = classify(graph, rng);
 
-    for (obj_y, row) in object.iter().enumerate() {
-        for (obj_x, cell) in row.iter().enumerate() {
-            if cell == &Objects::Wall {
-                room[y + 1 + obj_y + y_offset][x + 1 + obj_x + x_offset] = Objects::Wall;
-            }
+    for (index, (rect, room_type)) in rects.iter().zip(&types).enumerate() {
+        if rng.gen_range(0.0, 1.0) >= config.prefab_density {
+            continue;
         }
-    }
-}
 
-#[test]
-fn test() {
-    for _ in 0..15 {
-        let random = complete_random(&*LARGE_OBJECTS);
-        println!();
-        print_room(&random);
+        let room_doors = room_doors[index]
+            .iter()
+            .map(|door| doors[*door])
+            .collect::<Vec<_>>();
+        place_prefabs(room, rect, *room_type, &room_doors, rng);
     }
-}
 
-fn gen_battle_room(room: &mut [Vec<Objects>], rect: &Rect) {
-    paste_object(0, 0, &complete_random(&*LARGE_OBJECTS), rect, room);
+    types
 }
 
-pub enum Room {
-    Start,
-    End,
-    Battle,
-}
+#[test]
+fn room_types() {
+    use super::gen::generate_room;
+
+    for seed in 0..20 {
+        let floor = generate_room(seed, &GenConfig::default()).unwrap();
+        let count = |room_type| floor.room_types.iter().filter(|t| **t == room_type).count();
 
-pub fn post(room: &mut [Vec<Objects>], rects: &mut [Rect]) {
-    for rect in rects.iter_mut() {
-        gen_battle_room(room, rect);
+        assert_eq!(count(Room::Start), 1);
+        assert_eq!(count(Room::End), 1);
+        assert!(count(Room::Shop) <= 1);
     }
 }
@@ -0,0 +1,303 @@
+use lazy_static::lazy_static;
+
+use super::gen::Objects;
+use super::post::Room;
+use super::rng::Rng;
+use super::util::rotate_array;
+use crate::scenes::objects::shapes::rect::Rect;
+
+/// Prefabs don't get placed closer than this to a door of their room (in tiles)
+const DOOR_CLEARANCE: usize = 3;
+/// Random positions tried for each prefab before giving up on it
+const PLACE_ATTEMPTS: usize = 10;
+/// Interior tiles of a room per prefab placed in it
+const AREA_PER_PREFAB: usize = 150;
+const MAX_PREFABS: usize = 4;
+
+macro_rules! prefab_files {
+    ($( $name: literal ), *) => {
+        [$( ($name, include_str!(concat!("../../../assets/prefabs/", $name))) ),*]
+    };
+}
+
+/// Name and contents of every prefab file in `assets/prefabs`
+const FILES: [(&str, &str); 4] =
+    prefab_files!("small.txt", "medium.txt", "large.txt", "hazards.txt");
+
+lazy_static! {
+    pub static ref PREFABS: Vec<Prefab> = FILES
+        .iter()
+        .flat_map(|(name, text)| parse(text).unwrap_or_else(|err| panic!("{}: {}", name, err)))
+        .collect();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizeClass {
+    Small,
+    Medium,
+    Large,
+}
+impl SizeClass {
+    /// Side of the square the prefab's tiles are padded to
+    pub fn size(&self) -> usize {
+        match self {
+            SizeClass::Small => 2,
+            SizeClass::Medium => 3,
+            SizeClass::Large => 5,
+        }
+    }
+}
+
+/// Obstacle pasted into rooms by `post`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prefab {
+    pub size: SizeClass,
+    /// How likely the prefab is to be picked compared to the others
+    pub weight: usize,
+    /// Types of rooms the prefab can be placed in
+    pub rooms: Vec<Room>,
+    /// Smallest room side (in tiles, walls included) the prefab can be placed in
+    pub min_room: usize,
+    /// Square grid (`size.size()` wide), `Objects::Air` tiles leave the room untouched
+    pub tiles: Vec<Vec<Objects>>,
+}
+
+/// Parses a prefab file, prefabs are separated by blank lines and start with a header:
+/// ```text
+/// size: small | medium | large
+/// weight: 3
+/// rooms: battle, treasure
+/// min_room: 8
+/// ##.
+/// .~~
+/// ```
+/// Tiles are written with `Objects::symbol`, `.` being air
+pub fn parse(text: &str) -> Result<Vec<Prefab>, String> {
+    let mut prefabs = vec![];
+
+    // Files checked out with Windows line endings would otherwise be one big prefab
+    let text = text.replace("\r\n", "\n");
+    for (index, block) in text.split("\n\n").enumerate() {
+        let lines = block
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            continue;
+        }
+
+        let (mut size, mut weight, mut rooms, mut min_room) = (None, 1, vec![], 0);
+        let mut rows = vec![];
+        for line in lines {
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    rows.push(line);
+                    continue;
+                }
+            };
+
+            match key {
+                "size" => {
+                    size = Some(match value {
+                        "small" => SizeClass::Small,
+                        "medium" => SizeClass::Medium,
+                        "large" => SizeClass::Large,
+                        _ => return Err(format!("prefab {}: unknown size `{}`", index, value)),
+                    })
+                }
+                "weight" => {
+                    weight = value
+                        .parse()
+                        .map_err(|_| format!("prefab {}: invalid weight `{}`", index, value))?
+                }
+                "min_room" => {
+                    min_room = value
+                        .parse()
+                        .map_err(|_| format!("prefab {}: invalid min_room `{}`", index, value))?
+                }
+                "rooms" => {
+                    for name in value.split(',').map(str::trim) {
+                        rooms.push(match name {
+                            "start" => Room::Start,
+                            "end" => Room::End,
+                            "battle" => Room::Battle,
+                            "treasure" => Room::Treasure,
+                            "shop" => Room::Shop,
+                            _ => return Err(format!("prefab {}: unknown room `{}`", index, name)),
+                        });
+                    }
+                }
+                _ => return Err(format!("prefab {}: unknown key `{}`", index, key)),
+            }
+        }
+
+        let size = size.ok_or(format!("prefab {}: missing size", index))?;
+        if weight == 0 {
+            return Err(format!("prefab {}: weight of 0", index));
+        }
+        if rows.len() > size.size() || rows.iter().any(|row| row.chars().count() > size.size()) {
+            return Err(format!("prefab {}: bigger than {:?}", index, size));
+        }
+
+        let mut tiles = vec![vec![Objects::Air; size.size()]; size.size()];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, char) in row.chars().enumerate() {
+                tiles[y][x] = match char {
+                    '.' => Objects::Air,
+                    _ => Objects::from_symbol(char)
+                        .ok_or(format!("prefab {}: unknown tile `{}`", index, char))?,
+                };
+            }
+        }
+
+        prefabs.push(Prefab {
+            size,
+            weight,
+            rooms,
+            min_room,
+            tiles,
+        });
+    }
+
+    Ok(prefabs)
+}
+
+/// Weighted random prefab that fits in a room of `room_type` with `rect`
+fn pick<'a>(room_type: Room, rect: &Rect, rng: &mut Rng) -> Option<&'a Prefab> {
+    let side = rect.width.min(rect.height) as usize;
+    let fitting = PREFABS
+        .iter()
+        .filter(|prefab| prefab.rooms.contains(&room_type) && prefab.min_room <= side)
+        .collect::<Vec<_>>();
+
+    let total = fitting.iter().map(|prefab| prefab.weight).sum::<usize>();
+    if total == 0 {
+        return None;
+    }
+
+    let mut roll = rng.gen_range(0, total);
+    for prefab in fitting {
+        if roll < prefab.weight {
+            return Some(prefab);
+        }
+        roll -= prefab.weight;
+    }
+    None
+}
+
+/// Whether `tiles` can be pasted with its top left corner at `x`, `y`: every solid tile (and the
+/// tiles around it) has to be empty floor inside the room and away from the doors
+fn fits(
+    tiles: &[Vec<Objects>],
+    (x, y): (usize, usize),
+    rect: &Rect,
+    doors: &[(usize, usize)],
+    room: &[Vec<Objects>],
+) -> bool {
+    let (left, top) = (rect.get_left() as usize + 1, rect.get_top() as usize + 1);
+    let (right, bottom) = (rect.get_right() as usize, rect.get_bottom() as usize);
+
+    for (tile_y, row) in tiles.iter().enumerate() {
+        for (tile_x, tile) in row.iter().enumerate() {
+            if tile == &Objects::Air {
+                continue;
+            }
+            let (tx, ty) = (x + tile_x, y + tile_y);
+
+            if tx <= left || ty <= top || tx + 1 >= right || ty + 1 >= bottom {
+                return false;
+            }
+            let empty = room[ty - 1..=ty + 1].iter().all(|row| {
+                row[tx - 1..=tx + 1]
+                    .iter()
+                    .all(|cell| cell == &Objects::Air)
+            });
+            if !empty {
+                return false;
+            }
+
+            let near_door = doors.iter().any(|(door_x, door_y)| {
+                tx.abs_diff(*door_x).max(ty.abs_diff(*door_y)) <= DOOR_CLEARANCE
+            });
+            if near_door {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Pastes a few random prefabs at random spots in a room, skipping the ones that don't fit
+pub fn place_prefabs(
+    room: &mut [Vec<Objects>],
+    rect: &Rect,
+    room_type: Room,
+    doors: &[(usize, usize)],
+    rng: &mut Rng,
+) {
+    let area = (rect.width as usize).saturating_sub(1) * (rect.height as usize).saturating_sub(1);
+    let count = (1 + area / AREA_PER_PREFAB).min(MAX_PREFABS);
+
+    for _ in 0..count {
+        let prefab = match pick(room_type, rect, rng) {
+            Some(prefab) => prefab,
+            None => return,
+        };
+
+        let mut tiles = prefab.tiles.clone();
+        for _ in 0..rng.gen_range(0, 4) {
+            rotate_array(&mut tiles);
+        }
+
+        // Leaves room for the 1 tile margin `fits` checks around the prefab
+        let size = prefab.size.size();
+        let (min_x, min_y) = (rect.get_left() as usize + 2, rect.get_top() as usize + 2);
+        let (max_x, max_y) = (
+            (rect.get_right() as usize).saturating_sub(size + 1),
+            (rect.get_bottom() as usize).saturating_sub(size + 1),
+        );
+        if max_x <= min_x || max_y <= min_y {
+            continue;
+        }
+
+        for _ in 0..PLACE_ATTEMPTS {
+            let pos = (rng.gen_range(min_x, max_x), rng.gen_range(min_y, max_y));
+            if !fits(&tiles, pos, rect, doors, room) {
+                continue;
+            }
+
+            for (tile_y, row) in tiles.iter().enumerate() {
+                for (tile_x, tile) in row.iter().enumerate() {
+                    if tile != &Objects::Air {
+                        room[pos.1 + tile_y][pos.0 + tile_x] = *tile;
+                    }
+                }
+            }
+            break;
+        }
+    }
+}
+
+#[test]
+fn prefabs() {
+    assert!(!PREFABS.is_empty());
+    for prefab in PREFABS.iter() {
+        assert!(prefab.weight > 0);
+        assert!(!prefab.rooms.is_empty());
+    }
+
+    assert!(parse("size: tiny\n#").is_err());
+    assert!(parse("size: small\n###").is_err());
+    assert!(parse("weight: 1\n#").is_err());
+    assert!(parse("size: small\nweight: 0\n#").is_err());
+    assert!(parse("size: small\n#?").is_err());
+
+    // Windows line endings still split the prefabs
+    let text = "size: small\r\nrooms: battle\r\n#.\r\n\r\nsize: small\r\nrooms: shop\r\n.~";
+    let prefabs = parse(text).unwrap();
+    assert_eq!(prefabs.len(), 2);
+    assert_eq!(prefabs[1].tiles[0], vec![Objects::Air, Objects::Water]);
+}
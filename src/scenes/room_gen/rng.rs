@@ -0,0 +1,83 @@
+use macroquad::miniquad::date;
+
+const DEFAULT_INC: u64 = 1442695040888963407;
+const MULTIPLIER: u64 = 6364136223846793005;
+
+/// Self-contained PCG generator (same algorithm as `quad_rand`), threaded through every room_gen stage so a seed always produces the same dungeon
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let mut rng = Rng { state: 0 };
+        rng.rand();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.rand();
+        rng
+    }
+
+    /// Returns a pseudo-random number from `0` to `u32::MAX`
+    pub fn rand(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state.wrapping_mul(MULTIPLIER).wrapping_add(DEFAULT_INC);
+
+        let xor_shifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rot = (old_state >> 59) as u32;
+        xor_shifted.rotate_right(rot)
+    }
+
+    /// Returns a random value from `low` (inclusive) to `high` (exclusive)
+    pub fn gen_range<T: RandomRange>(&mut self, low: T, high: T) -> T {
+        T::gen_range(self, low, high)
+    }
+
+    /// Returns a random element of `array`
+    pub fn choose<'a, T>(&mut self, array: &'a [T]) -> &'a T {
+        if array.is_empty() {
+            panic!("Array is empty");
+        }
+        &array[self.gen_range(0, array.len())]
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Rng {
+        Rng::new(0)
+    }
+}
+
+pub trait RandomRange {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self;
+}
+
+impl RandomRange for f32 {
+    fn gen_range(rng: &mut Rng, low: f32, high: f32) -> f32 {
+        // 24 bits so the ratio is exactly representable and never reaches `1.0`
+        let ratio = (rng.rand() >> 8) as f32 / (1 << 24) as f32;
+        low + (high - low) * ratio
+    }
+}
+
+impl RandomRange for usize {
+    fn gen_range(rng: &mut Rng, low: usize, high: usize) -> usize {
+        if high <= low {
+            return low;
+        }
+        low + ((rng.rand() as u64 * (high - low) as u64) >> 32) as usize
+    }
+}
+
+impl RandomRange for i32 {
+    fn gen_range(rng: &mut Rng, low: i32, high: i32) -> i32 {
+        if high <= low {
+            return low;
+        }
+        low + ((rng.rand() as u64 * low.abs_diff(high) as u64) >> 32) as i32
+    }
+}
+
+/// Seed for a new run when the player didn't type one in
+pub fn random_seed() -> u64 {
+    (date::now() * 1000.0) as u64
+}
@@ -0,0 +1,188 @@
+use super::config::GenConfig;
+use super::error::GenError;
+use super::gen::Objects;
+use super::generators::enclose::SIDES;
+use super::generators::{attach, Corridor};
+use super::post::Room;
+use super::rng::Rng;
+use super::util::{draw_rect, get_tile, set_tile};
+use super::validate::{flood_fill, open_tile, rect_tiles};
+use crate::scenes::dungeon_manager::Floor;
+use crate::scenes::objects::shapes::rect::Rect;
+
+/// Smallest and biggest side of a secret room (in tiles, walls included)
+const SECRET_SIDES: (i32, i32) = (7, 11);
+/// Shortest and longest corridor leading to a secret room (in tiles)
+const SECRET_CORRIDOR: (i32, i32) = (2, 6);
+/// Tries at fitting a secret room next to every dead end
+const ATTEMPTS: usize = 12;
+
+/// Whether the secret room and its corridor only take up space nothing can walk to, with a
+/// tile of space around the room, and the corridor comes out of the dead end's wall
+fn fits(
+    floor: &Floor,
+    rect: &Rect,
+    corridor: &Corridor,
+    path_size: usize,
+    reached: &[Vec<bool>],
+) -> bool {
+    let size = floor.room.len() as f32;
+    if rect.get_left() < 2.0
+        || rect.get_top() < 2.0
+        || rect.get_right() > size - 3.0
+        || rect.get_bottom() > size - 3.0
+    {
+        return false;
+    }
+
+    let unused = |area: &Rect| {
+        rect_tiles(area).all(|(x, y)| {
+            get_tile(&floor.room, (x as i32, y as i32)) == Some(Objects::Air) && !reached[y][x]
+        })
+    };
+    let around = Rect::new(
+        rect.get_left() - 1.0,
+        rect.get_top() - 1.0,
+        rect.width + 2.0,
+        rect.height + 2.0,
+    );
+
+    // The tiles right inside the dead end have to be walkable to get to the secret wall
+    let entrance = corridor
+        .door_tiles(corridor.start, path_size)
+        .into_iter()
+        .all(|(x, y)| {
+            let (x, y) = (x as i32, y as i32);
+            let inside = (x - corridor.dir.0, y - corridor.dir.1);
+            get_tile(&floor.room, (x, y)) == Some(Objects::Wall)
+                && get_tile(&floor.room, inside).is_some_and(|tile| tile.properties().walkable)
+        });
+
+    entrance && unused(&around) && unused(&corridor.area(path_size))
+}
+
+/// Draws the secret room and its corridor, with a secret wall where the corridor meets the dead
+/// end. The room has no doors so nothing is shut when the player walks in
+fn carve(
+    floor: &mut Floor,
+    rect: Rect,
+    corridor: &Corridor,
+    path_size: usize,
+) -> Result<(), GenError> {
+    draw_rect(&rect, &mut floor.room)?;
+    corridor.carve_walls(&mut floor.room, path_size)?;
+    for (x, y) in corridor.door_tiles(corridor.start, path_size) {
+        set_tile(&mut floor.room, (x as i32, y as i32), Objects::SecretWall)?;
+    }
+    for (x, y) in corridor.door_tiles(corridor.end, path_size) {
+        set_tile(&mut floor.room, (x as i32, y as i32), Objects::Air)?;
+    }
+
+    floor.rects.push(rect);
+    floor.room_types.push(Room::Secret);
+    floor.room_doors.push(vec![]);
+    floor.graph.connect(corridor.from, corridor.to);
+    Ok(())
+}
+
+/// Hides up to `config.secret_rooms` rooms next to the dead ends of the floor (other than the
+/// start and end rooms), behind walls that have to be shot down
+pub fn add_secret_rooms(
+    floor: &mut Floor,
+    config: &GenConfig,
+    rng: &mut Rng,
+) -> Result<(), GenError> {
+    let start = match floor.start_room() {
+        Some(start) => start,
+        None => return Ok(()),
+    };
+    let start_tile = match open_tile(&floor.rects[start], &floor.room) {
+        Some(tile) => tile,
+        None => return Ok(()),
+    };
+    let reached = flood_fill(floor, start_tile);
+    let half_path_size = config.path_size as i32 / 2 + 1;
+
+    let mut added = 0;
+    for dead_end in floor.graph.dead_ends() {
+        if added >= config.secret_rooms {
+            break;
+        }
+        if matches!(floor.room_types[dead_end], Room::Start | Room::End) {
+            continue;
+        }
+
+        for _ in 0..ATTEMPTS {
+            let dir = *rng.choose(&SIDES);
+            let size = (
+                rng.gen_range(SECRET_SIDES.0, SECRET_SIDES.1 + 1),
+                rng.gen_range(SECRET_SIDES.0, SECRET_SIDES.1 + 1),
+            );
+            let length = rng.gen_range(SECRET_CORRIDOR.0, SECRET_CORRIDOR.1 + 1);
+            let from = &floor.rects[dead_end];
+            let (rect, start, end) = match attach(from, dir, size, length, half_path_size, rng) {
+                Some(attached) => attached,
+                None => continue,
+            };
+
+            let corridor = Corridor {
+                from: dead_end,
+                to: floor.rects.len(),
+                start,
+                end,
+                dir,
+            };
+            if fits(floor, &rect, &corridor, config.path_size, &reached) {
+                carve(floor, rect, &corridor, config.path_size)?;
+                added += 1;
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn secret_rooms() {
+    use super::config::PRESETS;
+    use super::gen::generate_room;
+    use super::generators::GENERATORS;
+    use super::validate::unreachable_rooms;
+
+    let mut found = 0;
+    for (generator, config) in GENERATORS
+        .into_iter()
+        .flat_map(|generator| PRESETS.map(|config| (generator, config)))
+    {
+        let config = GenConfig {
+            generator,
+            ..config
+        };
+        for seed in 0..5 {
+            let floor = generate_room(seed, &config).unwrap();
+            let secrets = (0..floor.rects.len())
+                .filter(|index| floor.room_types[*index] == Room::Secret)
+                .collect::<Vec<_>>();
+            assert!(secrets.len() <= config.secret_rooms);
+            found += secrets.len();
+
+            for secret in &secrets {
+                assert_eq!(floor.graph.neighbours(*secret).len(), 1);
+                assert!(floor.room_doors[*secret].is_empty());
+            }
+            assert_eq!(unreachable_rooms(&floor), vec![]);
+
+            // Without shooting the secret walls the secret rooms can't be walked to
+            let mut walled = floor.clone();
+            for row in &mut walled.room {
+                for tile in row {
+                    if *tile == Objects::SecretWall {
+                        *tile = Objects::Wall;
+                    }
+                }
+            }
+            assert_eq!(unreachable_rooms(&walled), secrets);
+        }
+    }
+    assert!(found > 0);
+}
@@ -0,0 +1,107 @@
+use super::gen::Objects;
+use super::post::Room;
+use super::rng::Rng;
+use super::validate::interior;
+use crate::scenes::dungeon_manager::Floor;
+
+/// Most spawn points stored for a single room
+const MAX_SPAWNS: usize = 16;
+/// Enemies don't spawn closer than this to a door (in tiles)
+const DOOR_DISTANCE: usize = 4;
+/// Minimum distance between the spawn points of a room (in tiles)
+const SPAWN_SPACING: usize = 2;
+
+/// Whether something one tile big can stand on `tile` without touching walls or prefab obstacles
+fn clear(tile: (usize, usize), room: &[Vec<Objects>]) -> bool {
+    let (x, y) = tile;
+    if x == 0 || y == 0 || y + 1 >= room.len() || x + 1 >= room[y].len() {
+        return false;
+    }
+
+    room[y - 1..=y + 1]
+        .iter()
+        .all(|row| row[x - 1..=x + 1].iter().all(|cell| cell == &Objects::Air))
+}
+
+fn tile_distance(a: (usize, usize), b: (usize, usize)) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+/// Clear tile closest to the center of the start room
+pub fn player_spawn(floor: &Floor) -> Option<(usize, usize)> {
+    let start = floor.start_room()?;
+    let rect = floor.rects[start];
+    let center = (rect.get_center().x as usize, rect.get_center().y as usize);
+
+    interior(&rect)
+        .filter(|tile| clear(*tile, &floor.room))
+        .min_by_key(|tile| tile_distance(*tile, center))
+}
+
+/// Spread out clear tiles away from doors for every battle room, empty for other rooms
+pub fn enemy_spawns(floor: &Floor, rng: &mut Rng) -> Vec<Vec<(usize, usize)>> {
+    let mut spawns = vec![vec![]; floor.rects.len()];
+
+    for (index, room_type) in floor.room_types.iter().enumerate() {
+        if room_type != &Room::Battle {
+            continue;
+        }
+
+        let doors = floor.room_doors[index]
+            .iter()
+            .map(|door| floor.doors[*door])
+            .collect::<Vec<_>>();
+        let mut candidates = interior(&floor.rects[index])
+            .filter(|tile| clear(*tile, &floor.room))
+            .filter(|tile| {
+                doors
+                    .iter()
+                    .all(|door| tile_distance(*tile, *door) >= DOOR_DISTANCE)
+            })
+            .collect::<Vec<_>>();
+
+        // Fisher-Yates so the picked points are random but still come from the floor's seed
+        for i in (1..candidates.len()).rev() {
+            candidates.swap(i, rng.gen_range(0, i + 1));
+        }
+
+        for candidate in candidates {
+            if spawns[index].len() >= MAX_SPAWNS {
+                break;
+            }
+            if spawns[index]
+                .iter()
+                .all(|spawn| tile_distance(*spawn, candidate) >= SPAWN_SPACING)
+            {
+                spawns[index].push(candidate);
+            }
+        }
+    }
+
+    spawns
+}
+
+#[test]
+fn spawns() {
+    use macroquad::prelude::vec2;
+
+    use super::config::GenConfig;
+    use super::gen::generate_room;
+
+    for seed in 0..20 {
+        let floor = generate_room(seed, &GenConfig::default()).unwrap();
+
+        let player = floor.player_spawn.unwrap();
+        assert_eq!(
+            floor.room_types[floor.room_index(player.0, player.1).unwrap()],
+            Room::Start
+        );
+
+        for (index, spawns) in floor.enemy_spawns.iter().enumerate() {
+            for spawn in spawns {
+                assert!(clear(*spawn, &floor.room));
+                assert!(floor.rects[index].touches_point(&vec2(spawn.0 as f32, spawn.1 as f32)));
+            }
+        }
+    }
+}
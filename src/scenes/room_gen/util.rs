@@ -2,19 +2,16 @@ use std::collections::HashSet;
 use std::fmt::Debug;
 use std::mem::swap;
 
-use macroquad::prelude::rand::gen_range;
-
+use super::config::GenConfig;
+use super::error::GenError;
 use super::gen::{Objects, SplitQueue};
+use super::rng::Rng;
 use crate::scenes::objects::shapes::rect::Rect;
 
 pub fn print_room(rooms: &[Vec<Objects>]) {
     for row in rooms {
         for cell in row {
-            let sym = match cell {
-                Objects::Air => " ",
-                Objects::Wall => "#",
-            };
-            print!("{}", sym);
+            print!("{}", cell.symbol());
         }
         println!();
     }
@@ -37,11 +34,11 @@ where
     }
 }
 
-pub fn pop_random<T>(raw: &mut Vec<T>) -> Option<T> {
+pub fn pop_random<T>(raw: &mut Vec<T>, rng: &mut Rng) -> Option<T> {
     if raw.is_empty() {
         return None;
     }
-    let i = gen_range(0, raw.len());
+    let i = rng.gen_range(0, raw.len());
     Some(raw.swap_remove(i))
 }
 
@@ -129,8 +126,8 @@ pub fn find_rect(
     ))
 }
 
-pub fn rand_rect(rect: &mut Rect) {
-    let scale = gen_range::<f32>(0.7, 0.9);
+pub fn rand_rect(rect: &mut Rect, config: &GenConfig, rng: &mut Rng) {
+    let scale = rng.gen_range::<f32>(config.shrink_range.0, config.shrink_range.1);
 
     let x_diff = (rect.width - rect.width * scale).abs();
     let y_diff = (rect.height - rect.height * scale).abs();
@@ -138,8 +135,8 @@ pub fn rand_rect(rect: &mut Rect) {
     rect.width = (rect.width * scale).round();
     rect.height = (rect.height * scale).round();
 
-    rect.pos.x = (rect.pos.x + x_diff * gen_range(0.0, 1.0)).round();
-    rect.pos.y = (rect.pos.y + y_diff * gen_range(0.0, 1.0)).round();
+    rect.pos.x = (rect.pos.x + x_diff * rng.gen_range(0.0, 1.0)).round();
+    rect.pos.y = (rect.pos.y + y_diff * rng.gen_range(0.0, 1.0)).round();
 }
 
 #[macro_export]
@@ -156,17 +153,48 @@ macro_rules! vec_remove {
     };
 }
 
-pub fn draw_rect(rect: &Rect, room: &mut [Vec<Objects>]) {
-    for x in rect.pos.x as usize..=rect.get_right() as usize {
-        room[rect.pos.y as usize][x] = Objects::Wall;
-        room[rect.get_bottom() as usize][x] = Objects::Wall;
+/// Tile at `x`, `y`, `None` outside of the map
+pub fn get_tile(room: &[Vec<Objects>], (x, y): (i32, i32)) -> Option<Objects> {
+    if x < 0 || y < 0 {
+        return None;
     }
-    for row in room
-        .iter_mut()
-        .take(rect.get_bottom() as usize + 1)
-        .skip(rect.pos.y as usize)
-    {
-        row[rect.pos.x as usize] = Objects::Wall;
-        row[rect.get_right() as usize] = Objects::Wall;
+    room.get(y as usize)
+        .and_then(|row| row.get(x as usize))
+        .copied()
+}
+
+/// Sets the tile at `x`, `y`, erroring instead of panicking outside of the map
+pub fn set_tile(
+    room: &mut [Vec<Objects>],
+    (x, y): (i32, i32),
+    tile: Objects,
+) -> Result<(), GenError> {
+    let size = room.len();
+    let cell = if x < 0 || y < 0 {
+        None
+    } else {
+        room.get_mut(y as usize)
+            .and_then(|row| row.get_mut(x as usize))
+    };
+    match cell {
+        Some(cell) => {
+            *cell = tile;
+            Ok(())
+        }
+        None => Err(GenError::OutOfBounds { x, y, size }),
+    }
+}
+
+pub fn draw_rect(rect: &Rect, room: &mut [Vec<Objects>]) -> Result<(), GenError> {
+    let (left, top) = (rect.get_left() as i32, rect.get_top() as i32);
+    let (right, bottom) = (rect.get_right() as i32, rect.get_bottom() as i32);
+    for x in left..=right {
+        set_tile(room, (x, top), Objects::Wall)?;
+        set_tile(room, (x, bottom), Objects::Wall)?;
+    }
+    for y in top..=bottom {
+        set_tile(room, (left, y), Objects::Wall)?;
+        set_tile(room, (right, y), Objects::Wall)?;
     }
+    Ok(())
 }
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+use super::error::GenError;
+use super::gen::Objects;
+use super::generators::Layout;
+use crate::scenes::dungeon_manager::Floor;
+use crate::scenes::objects::shapes::rect::Rect;
+
+/// Tiles inside the walls of `rect`
+pub fn interior(rect: &Rect) -> impl Iterator<Item = (usize, usize)> {
+    let (left, top) = (rect.get_left() as usize + 1, rect.get_top() as usize + 1);
+    let (right, bottom) = (rect.get_right() as usize, rect.get_bottom() as usize);
+    (top..bottom).flat_map(move |y| (left..right).map(move |x| (x, y)))
+}
+
+/// Tiles of `rect`, walls included
+pub fn rect_tiles(rect: &Rect) -> impl Iterator<Item = (usize, usize)> {
+    let (left, top) = (rect.get_left() as usize, rect.get_top() as usize);
+    let (right, bottom) = (rect.get_right() as usize, rect.get_bottom() as usize);
+    (top..=bottom).flat_map(move |y| (left..=right).map(move |x| (x, y)))
+}
+
+/// Open tile of a room closest to its center
+pub fn open_tile(rect: &Rect, room: &[Vec<Objects>]) -> Option<(usize, usize)> {
+    let center = rect.get_center();
+    interior(rect)
+        .filter(|(x, y)| room[*y][*x] == Objects::Air)
+        .min_by_key(|(x, y)| (x.abs_diff(center.x as usize) + y.abs_diff(center.y as usize)) as u32)
+}
+
+/// Checks that everything in a generator's layout is on the map and refers to rooms and doors that
+/// exist, so the later stages can index with it
+pub fn check_layout(layout: &Layout, size: usize) -> Result<(), GenError> {
+    let out_of_bounds = |x: f32, y: f32| GenError::OutOfBounds {
+        x: x as i32,
+        y: y as i32,
+        size,
+    };
+
+    if layout.room.len() != size {
+        return Err(out_of_bounds(0.0, layout.room.len() as f32));
+    }
+    for (y, row) in layout.room.iter().enumerate() {
+        if row.len() != size {
+            return Err(out_of_bounds(row.len() as f32, y as f32));
+        }
+    }
+
+    for rect in &layout.rects {
+        if rect.get_left() < 0.0 || rect.get_top() < 0.0 {
+            return Err(out_of_bounds(rect.get_left(), rect.get_top()));
+        }
+        if rect.get_right() >= size as f32 || rect.get_bottom() >= size as f32 {
+            return Err(out_of_bounds(rect.get_right(), rect.get_bottom()));
+        }
+    }
+    for (x, y) in &layout.doors {
+        if *x >= size || *y >= size {
+            return Err(out_of_bounds(*x as f32, *y as f32));
+        }
+    }
+
+    let rooms = layout.rects.len();
+    if layout.room_doors.len() != rooms {
+        return Err(GenError::MissingRoom(rooms.min(layout.room_doors.len())));
+    }
+    if let Some(door) = layout
+        .room_doors
+        .iter()
+        .flatten()
+        .find(|door| **door >= layout.doors.len())
+    {
+        return Err(GenError::MissingDoor(*door));
+    }
+    for (index, rs) in &layout.adjacents {
+        if let Some(missing) = [*index]
+            .into_iter()
+            .chain(rs.iter().map(|(r, _)| *r))
+            .find(|r| *r >= rooms)
+        {
+            return Err(GenError::MissingRoom(missing));
+        }
+    }
+
+    Ok(())
+}
+
+/// Walks from `start` over walkable tiles and through tiles that can be shot down, returns which
+/// tiles were reached
+pub fn flood_fill(floor: &Floor, start: (usize, usize)) -> Vec<Vec<bool>> {
+    let mut reached = floor
+        .room
+        .iter()
+        .map(|row| vec![false; row.len()])
+        .collect::<Vec<_>>();
+
+    let mut queue = VecDeque::from([start]);
+    reached[start.1][start.0] = true;
+    while let Some((x, y)) = queue.pop_front() {
+        for (nx, ny) in [
+            (x + 1, y),
+            (x.wrapping_sub(1), y),
+            (x, y + 1),
+            (x, y.wrapping_sub(1)),
+        ] {
+            if ny >= floor.room.len() || nx >= floor.room[ny].len() || reached[ny][nx] {
+                continue;
+            }
+
+            let properties = floor.room[ny][nx].properties();
+            if properties.walkable || properties.destructible {
+                reached[ny][nx] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    reached
+}
+
+/// Rooms that can't be walked to from the start room
+pub fn unreachable_rooms(floor: &Floor) -> Vec<usize> {
+    let start = match floor.start_room() {
+        Some(start) => start,
+        None => return vec![],
+    };
+    let start_tile = match open_tile(&floor.rects[start], &floor.room) {
+        Some(tile) => tile,
+        None => return (0..floor.rects.len()).collect(),
+    };
+
+    let reached = flood_fill(floor, start_tile);
+    (0..floor.rects.len())
+        .filter(|index| !interior(&floor.rects[*index]).any(|(x, y)| reached[y][x]))
+        .collect()
+}
+
+/// Clears the prefab obstacles out of unreachable rooms and the rooms connected to them,
+/// returns `false` if there are still rooms that can't be reached
+pub fn repair(floor: &mut Floor) -> bool {
+    let unreachable = unreachable_rooms(floor);
+    if unreachable.is_empty() {
+        return true;
+    }
+
+    let mut blocked = unreachable.clone();
+    for room in &unreachable {
+        blocked.extend(floor.graph.neighbours(*room));
+    }
+
+    for index in blocked {
+        for (x, y) in interior(&floor.rects[index]) {
+            floor.room[y][x] = Objects::Air;
+        }
+    }
+
+    unreachable_rooms(floor).is_empty()
+}
+
+#[test]
+fn connectivity() {
+    use super::config::PRESETS;
+    use super::gen::generate_room;
+
+    for config in &PRESETS {
+        for seed in 0..100 {
+            let floor = generate_room(seed, config).unwrap();
+            assert_eq!(
+                unreachable_rooms(&floor),
+                vec![],
+                "{} seed {}",
+                config.name,
+                seed
+            );
+        }
+    }
+
+    // Walling off every door must be detected
+    let mut floor = generate_room(0, &PRESETS[0]).unwrap();
+    for (x, y) in floor.doors.clone() {
+        floor.room[y][x] = Objects::Wall;
+    }
+    assert!(!unreachable_rooms(&floor).is_empty());
+}
@@ -0,0 +1,536 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::scenes::game::RunStats;
+
+/// Turns the `key value` lines of a save into those of the next version
+type Migration = fn(&mut Vec<(String, String)>);
+
+/// `MIGRATIONS[n]` upgrades version `n + 1` saves to version `n + 2`. When the format changes,
+/// add a migration instead of changing how older saves are read, so they keep loading
+const MIGRATIONS: [Migration; 3] = [add_stats, add_enemy_kinds, add_enemy_states];
+
+/// Version of the saves written by `Save::to_text`
+pub const SAVE_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// First word of every save file, followed by the version
+const HEADER: &str = "rogueman save";
+
+/// Version 2 added the run's stats, older runs start counting from the load
+fn add_stats(lines: &mut Vec<(String, String)>) {
+    lines.push(("stats".to_owned(), "0 0 0".to_owned()));
+}
+
+/// Version 3 added enemy kinds, the enemies before all chased the player like chargers
+fn add_enemy_kinds(lines: &mut Vec<(String, String)>) {
+    *lines = std::mem::take(lines)
+        .into_iter()
+        .map(|(key, value)| match key.as_str() {
+            "enemy" => (key, format!("Charger {}", value)),
+            _ => (key, value),
+        })
+        .collect();
+}
+
+/// Version 4 added what enemies were up to and their home. The enemies of the locked room had
+/// noticed the player, the others stood around where they were
+fn add_enemy_states(lines: &mut Vec<(String, String)>) {
+    let locked = lines
+        .iter()
+        .find(|(key, _)| key == "locked")
+        .map(|(_, value)| value.clone());
+    *lines = std::mem::take(lines)
+        .into_iter()
+        .map(|(key, value)| {
+            let words = value.split_whitespace().collect::<Vec<_>>();
+            // Broken lines are left for `parse` to reject
+            if key != "enemy" || words.len() < 5 {
+                return (key, value);
+            }
+
+            let room = words.get(5).copied();
+            let state = match room {
+                Some(room) if Some(room) == locked.as_deref() => "chase",
+                _ => "idle",
+            };
+            let mut migrated = words[..5].to_vec();
+            migrated.push(state);
+            migrated.extend(&words[1..3]);
+            migrated.extend(room);
+            (key, migrated.join(" "))
+        })
+        .collect();
+}
+
+/// Snapshot of a run, the floors themselves are generated again from the seed when loading
+#[derive(Clone, Debug, PartialEq)]
+pub struct Save {
+    pub seed: u64,
+    /// Name of the `GenConfig` preset of the first floor
+    pub preset: String,
+    /// Name of the generator of the first floor
+    pub generator: String,
+    pub depth: usize,
+    pub stats: RunStats,
+    /// Whether each room of the current floor has been cleared of enemies
+    pub cleared: Vec<bool>,
+    pub locked_room: Option<usize>,
+    /// Tiles of the current floor that got shot down
+    pub broken: Vec<(usize, usize)>,
+    pub player: PlayerSave,
+    pub enemies: Vec<EnemySave>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerSave {
+    /// World position of the center of the player
+    pub position: (f32, f32),
+    pub health: f32,
+    pub max_health: f32,
+    /// Name and level of each gun
+    pub guns: Vec<(String, u8)>,
+    pub selected_gun: usize,
+    /// Name of each melee weapon
+    pub melees: Vec<String>,
+    pub selected_melee: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnemySave {
+    /// Name of its `EnemyKind`
+    pub kind: String,
+    /// World position of the center of the enemy
+    pub position: (f32, f32),
+    pub health: f32,
+    pub max_health: f32,
+    /// Name of its `AiState`, see `AiState::name`
+    pub state: String,
+    /// World position it patrols around
+    pub home: (f32, f32),
+    pub room: Option<usize>,
+}
+
+impl Save {
+    /// Writes the save as `key value` lines after a versioned header:
+    /// ```text
+    /// rogueman save 4
+    /// seed 1234
+    /// preset Medium
+    /// generator bsp
+    /// depth 1
+    /// stats 12 3 421.5
+    /// cleared 1 0 1
+    /// locked 1
+    /// broken 12 30
+    /// player 1200 870.5 80 100
+    /// gun 1 Pistol
+    /// selected_gun 0
+    /// melee Pocket Knife
+    /// selected_melee 0
+    /// enemy Shooter 1400 900 8 8 chase 1380 920 1
+    /// ```
+    /// `stats` are kills, rooms cleared and seconds played, `locked` is left out when no room is
+    /// locked and `enemy` is the kind followed by the position, health, max health, state, home
+    /// and room
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("{} {}", HEADER, SAVE_VERSION),
+            format!("seed {}", self.seed),
+            format!("preset {}", self.preset),
+            format!("generator {}", self.generator),
+            format!("depth {}", self.depth),
+            format!(
+                "stats {} {} {}",
+                self.stats.kills, self.stats.rooms_cleared, self.stats.time
+            ),
+            format!("cleared {}", join(self.cleared.iter().map(|c| *c as u8))),
+        ];
+        if let Some(room) = self.locked_room {
+            lines.push(format!("locked {}", room));
+        }
+        for (x, y) in &self.broken {
+            lines.push(format!("broken {} {}", x, y));
+        }
+
+        let player = &self.player;
+        lines.push(format!(
+            "player {} {} {} {}",
+            player.position.0, player.position.1, player.health, player.max_health
+        ));
+        for (name, level) in &player.guns {
+            lines.push(format!("gun {} {}", level, name));
+        }
+        lines.push(format!("selected_gun {}", player.selected_gun));
+        for name in &player.melees {
+            lines.push(format!("melee {}", name));
+        }
+        lines.push(format!("selected_melee {}", player.selected_melee));
+
+        for enemy in &self.enemies {
+            let mut line = format!(
+                "enemy {} {} {} {} {} {} {} {}",
+                enemy.kind,
+                enemy.position.0,
+                enemy.position.1,
+                enemy.health,
+                enemy.max_health,
+                enemy.state,
+                enemy.home.0,
+                enemy.home.1
+            );
+            if let Some(room) = enemy.room {
+                line += &format!(" {}", room);
+            }
+            lines.push(line);
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    /// Reads a save written by `to_text`, migrating it first if it is from an older version
+    pub fn parse(text: &str) -> Result<Save, String> {
+        Save::parse_with(text, &MIGRATIONS)
+    }
+
+    fn parse_with(text: &str, migrations: &[Migration]) -> Result<Save, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let version = lines
+            .next()
+            .and_then(|header| header.strip_prefix(HEADER))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or("not a save file")?;
+
+        let mut lines = lines
+            .map(|line| {
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                (key.to_owned(), value.trim().to_owned())
+            })
+            .collect::<Vec<_>>();
+        migrate(&mut lines, version, migrations)?;
+
+        let (mut seed, mut preset, mut generator, mut depth) = (None, None, None, None);
+        let mut stats = None;
+        let (mut cleared, mut locked_room, mut broken) = (vec![], None, vec![]);
+        let mut player = None;
+        let (mut guns, mut selected_gun) = (vec![], 0);
+        let (mut melees, mut selected_melee) = (vec![], 0);
+        let mut enemies = vec![];
+
+        for (key, value) in &lines {
+            match key.as_str() {
+                "seed" => seed = Some(number(key, value)?),
+                "preset" => preset = Some(value.clone()),
+                "generator" => generator = Some(value.clone()),
+                "depth" => depth = Some(number(key, value)?),
+                "stats" => match value.split_whitespace().collect::<Vec<_>>()[..] {
+                    [kills, rooms_cleared, time] => {
+                        stats = Some(RunStats {
+                            kills: number(key, kills)?,
+                            rooms_cleared: number(key, rooms_cleared)?,
+                            time: number(key, time)?,
+                        })
+                    }
+                    _ => return Err(invalid(key, value)),
+                },
+                "cleared" => {
+                    cleared = numbers::<u8>(key, value)?
+                        .into_iter()
+                        .map(|c| c != 0)
+                        .collect()
+                }
+                "locked" => locked_room = Some(number(key, value)?),
+                "broken" => match numbers(key, value)?[..] {
+                    [x, y] => broken.push((x, y)),
+                    _ => return Err(invalid(key, value)),
+                },
+                "player" => match numbers(key, value)?[..] {
+                    [x, y, health, max_health] => player = Some(((x, y), health, max_health)),
+                    _ => return Err(invalid(key, value)),
+                },
+                "gun" => {
+                    let (level, name) = value.split_once(' ').ok_or(invalid(key, value))?;
+                    guns.push((name.trim().to_owned(), number(key, level)?));
+                }
+                "selected_gun" => selected_gun = number(key, value)?,
+                "melee" => melees.push(value.clone()),
+                "selected_melee" => selected_melee = number(key, value)?,
+                "enemy" => {
+                    let (kind, value) = value.split_once(' ').ok_or(invalid(key, value))?;
+                    let words = value.split_whitespace().collect::<Vec<_>>();
+                    let room = match words.len() {
+                        7 => None,
+                        8 => Some(number(key, words[7])?),
+                        _ => return Err(invalid(key, value)),
+                    };
+                    let stats = numbers::<f32>(key, &words[..4].join(" "))?;
+                    let home = numbers::<f32>(key, &words[5..7].join(" "))?;
+                    enemies.push(EnemySave {
+                        kind: kind.to_owned(),
+                        position: (stats[0], stats[1]),
+                        health: stats[2],
+                        max_health: stats[3],
+                        state: words[4].to_owned(),
+                        home: (home[0], home[1]),
+                        room,
+                    });
+                }
+                _ => return Err(format!("unknown key `{}`", key)),
+            }
+        }
+
+        let (position, health, max_health) = player.ok_or("missing player")?;
+        Ok(Save {
+            seed: seed.ok_or("missing seed")?,
+            preset: preset.ok_or("missing preset")?,
+            generator: generator.ok_or("missing generator")?,
+            depth: depth.ok_or("missing depth")?,
+            stats: stats.ok_or("missing stats")?,
+            cleared,
+            locked_room,
+            broken,
+            player: PlayerSave {
+                position,
+                health,
+                max_health,
+                guns,
+                selected_gun,
+                melees,
+                selected_melee,
+            },
+            enemies,
+        })
+    }
+}
+
+/// Runs the migrations needed to bring the lines of a `version` save up to date, in order
+fn migrate(
+    lines: &mut Vec<(String, String)>,
+    version: u32,
+    migrations: &[Migration],
+) -> Result<(), String> {
+    let current = migrations.len() as u32 + 1;
+    if version == 0 || version > current {
+        return Err(format!(
+            "save version {} is not supported, this build reads versions 1 to {}",
+            version, current
+        ));
+    }
+
+    for migration in &migrations[version as usize - 1..] {
+        migration(lines);
+    }
+    Ok(())
+}
+
+fn invalid(key: &str, value: &str) -> String {
+    format!("invalid {} `{}`", key, value)
+}
+
+fn number<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| invalid(key, value))
+}
+
+fn numbers<T: FromStr>(key: &str, value: &str) -> Result<Vec<T>, String> {
+    value
+        .split_whitespace()
+        .map(|word| number(key, word))
+        .collect()
+}
+
+fn join<T: Display>(values: impl Iterator<Item = T>) -> String {
+    values
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/* --------------------------------- Storage -------------------------------- */
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_FILE: &str = "rogueman.save";
+
+/// Stores the save text, in `rogueman.save` next to the game
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(text: &str) -> Result<(), String> {
+    std::fs::write(SAVE_FILE, text).map_err(|err| format!("{}: {}", SAVE_FILE, err))
+}
+
+/// Reads back the text stored by `write`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read() -> Result<String, String> {
+    std::fs::read_to_string(SAVE_FILE).map_err(|err| format!("{}: {}", SAVE_FILE, err))
+}
+
+// Implemented by the `rogueman_storage` plugin in `server/index.html`, on top of local storage
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    fn rogueman_storage_set(ptr: *const u8, len: u32);
+    /// Length in bytes of the stored save, negative when there is none
+    fn rogueman_storage_len() -> i32;
+    fn rogueman_storage_get(ptr: *mut u8, len: u32);
+}
+
+/// Version of the JS plugin this build expects, checked by miniquad when loading (1.0.0)
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn rogueman_storage_crate_version() -> u32 {
+    1 << 24
+}
+
+/// Stores the save text in the browser's local storage
+#[cfg(target_arch = "wasm32")]
+pub fn write(text: &str) -> Result<(), String> {
+    unsafe { rogueman_storage_set(text.as_ptr(), text.len() as u32) };
+    Ok(())
+}
+
+/// Reads back the text stored by `write`
+#[cfg(target_arch = "wasm32")]
+pub fn read() -> Result<String, String> {
+    let len = unsafe { rogueman_storage_len() };
+    if len < 0 {
+        return Err("no save in local storage".to_owned());
+    }
+
+    let mut bytes = vec![0; len as usize];
+    unsafe { rogueman_storage_get(bytes.as_mut_ptr(), len as u32) };
+    String::from_utf8(bytes).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+fn example() -> Save {
+    Save {
+        seed: u64::MAX,
+        preset: "Medium".to_owned(),
+        generator: "bsp".to_owned(),
+        depth: 2,
+        stats: RunStats {
+            kills: 12,
+            rooms_cleared: 3,
+            time: 421.5,
+        },
+        cleared: vec![true, false, true],
+        locked_room: Some(1),
+        broken: vec![(12, 30), (13, 30)],
+        player: PlayerSave {
+            position: (1200.25, 870.5),
+            health: 72.5,
+            max_health: 100.0,
+            guns: vec![("Pistol".to_owned(), 1), ("SMG".to_owned(), 3)],
+            selected_gun: 1,
+            melees: vec!["Pocket Knife".to_owned()],
+            selected_melee: 0,
+        },
+        enemies: vec![
+            EnemySave {
+                kind: "Shooter".to_owned(),
+                position: (1400.0, 900.125),
+                health: 4.0,
+                max_health: 10.0,
+                state: "chase".to_owned(),
+                home: (1380.0, 920.5),
+                room: Some(1),
+            },
+            EnemySave {
+                kind: "Charger".to_owned(),
+                position: (-3.5, 0.1),
+                health: 10.0,
+                max_health: 10.0,
+                state: "patrol".to_owned(),
+                home: (0.0, -64.0),
+                room: None,
+            },
+        ],
+    }
+}
+
+#[test]
+fn round_trip() {
+    let save = example();
+    assert_eq!(Save::parse(&save.to_text()), Ok(save.clone()));
+
+    let unlocked = Save {
+        locked_room: None,
+        enemies: vec![],
+        ..save
+    };
+    assert_eq!(Save::parse(&unlocked.to_text()), Ok(unlocked));
+
+    assert!(Save::parse("").is_err());
+    assert!(Save::parse("rogueman save 0\n").is_err());
+    assert!(Save::parse(&format!("{} {}\n", HEADER, SAVE_VERSION + 1)).is_err());
+    assert!(Save::parse(&example().to_text().replace("depth 2", "depth two")).is_err());
+    assert!(Save::parse(&example().to_text().replace("seed", "sed")).is_err());
+}
+
+#[test]
+fn migrations() {
+    // Pretend the depth was saved as `level` in version 1, as `floor` in version 2 and as
+    // `depth` since version 3
+    fn rename(lines: &mut [(String, String)], from: &str, to: &str) {
+        for (key, _) in lines.iter_mut() {
+            if key == from {
+                *key = to.to_owned();
+            }
+        }
+    }
+    let migrations: [Migration; 2] = [
+        |lines| rename(lines, "level", "floor"),
+        |lines| rename(lines, "floor", "depth"),
+    ];
+
+    let text = example().to_text();
+    let header = format!("{} {}", HEADER, SAVE_VERSION);
+    let with_version = |version: u32, key: &str| {
+        text.replacen(&header, &format!("{} {}", HEADER, version), 1)
+            .replace("depth", key)
+    };
+
+    let old = with_version(1, "level");
+    assert_eq!(Save::parse_with(&old, &migrations), Ok(example()));
+
+    // Only the migrations after the save's version run
+    let middle = with_version(2, "floor");
+    assert_eq!(Save::parse_with(&middle, &migrations), Ok(example()));
+    assert!(Save::parse_with(&with_version(2, "level"), &migrations).is_err());
+
+    let newest = with_version(3, "depth");
+    assert_eq!(Save::parse_with(&newest, &migrations), Ok(example()));
+    let newer = with_version(SAVE_VERSION + 1, "depth");
+    assert!(Save::parse_with(&newer, &MIGRATIONS).is_err());
+
+    // Version 3 saves had no enemy states, the enemies of the locked room come back chasing
+    let version_3 = text
+        .replacen(&header, &format!("{} 3", HEADER), 1)
+        .replace(" chase 1380 920.5", "")
+        .replace(" patrol 0 -64", "");
+    let mut expected = example();
+    for enemy in &mut expected.enemies {
+        enemy.home = enemy.position;
+    }
+    expected.enemies[1].state = "idle".to_owned();
+    assert_eq!(Save::parse(&version_3), Ok(expected.clone()));
+    let unlocked = version_3.replace("locked 1\n", "");
+    let enemies = Save::parse(&unlocked).unwrap().enemies;
+    assert!(enemies.iter().all(|enemy| enemy.state == "idle"));
+
+    // Version 2 saves had no enemy kinds, every enemy was a charger
+    let version_2 = version_3
+        .replacen(&format!("{} 3", HEADER), &format!("{} 2", HEADER), 1)
+        .replace("enemy Shooter", "enemy")
+        .replace("enemy Charger", "enemy");
+    for enemy in &mut expected.enemies {
+        enemy.kind = "Charger".to_owned();
+    }
+    assert_eq!(Save::parse(&version_2), Ok(expected.clone()));
+
+    // Version 1 saves had no stats either
+    let stats = text.lines().find(|line| line.starts_with("stats")).unwrap();
+    let version_1 = version_2
+        .replacen(&format!("{} 2", HEADER), &format!("{} 1", HEADER), 1)
+        .replace(&format!("{}\n", stats), "");
+    let expected = Save {
+        stats: RunStats::default(),
+        ..expected
+    };
+    assert_eq!(Save::parse(&version_1), Ok(expected));
+}
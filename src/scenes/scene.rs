@@ -0,0 +1,192 @@
+use super::object::Object;
+
+/// Screen of the game, only the scene on top of the `SceneStack` gets updated (and so gets input)
+pub trait Scene: Object {
+    /// Called when the scene is put on the stack
+    fn enter(&mut self) {}
+
+    /// Called when the scene is taken off the stack
+    fn exit(&mut self) {}
+
+    /// Change of scene wanted after the last update, checked once per update
+    fn transition(&mut self) -> Option<Transition> {
+        None
+    }
+
+    /// Whether the scenes under this one are still drawn, behind it
+    fn overlay(&self) -> bool {
+        false
+    }
+}
+
+pub enum Transition {
+    /// Puts a scene on top of the current one, which is left as is until it is on top again
+    Push(Box<dyn Scene>),
+    /// Takes the current scene off, going back to the one under it
+    Pop,
+    /// Takes every scene off and starts over from a new one
+    Replace(Box<dyn Scene>),
+}
+
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+}
+impl SceneStack {
+    pub fn new(mut scene: Box<dyn Scene>) -> SceneStack {
+        scene.enter();
+        SceneStack {
+            scenes: vec![scene],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn apply(&mut self, transition: Transition) {
+        match transition {
+            Transition::Push(mut scene) => {
+                scene.enter();
+                self.scenes.push(scene);
+            }
+            Transition::Pop => {
+                if let Some(mut scene) = self.scenes.pop() {
+                    scene.exit();
+                }
+            }
+            Transition::Replace(mut scene) => {
+                while let Some(mut old) = self.scenes.pop() {
+                    old.exit();
+                }
+                scene.enter();
+                self.scenes.push(scene);
+            }
+        }
+    }
+}
+impl Object for SceneStack {
+    fn update(&mut self) {
+        let top = match self.scenes.last_mut() {
+            Some(top) => top,
+            None => return,
+        };
+        top.update();
+        if let Some(transition) = top.transition() {
+            self.apply(transition);
+        }
+    }
+
+    /// Draws the top scene, and the scenes under it up to the first one that isn't an overlay
+    fn draw(&mut self) {
+        let bottom = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.overlay())
+            .unwrap_or(0);
+        for scene in &mut self.scenes[bottom..] {
+            scene.draw();
+        }
+    }
+}
+
+#[test]
+fn scene_stack() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Writes down everything that happens to it in `log`
+    struct Logged {
+        name: &'static str,
+        overlay: bool,
+        log: Rc<RefCell<Vec<String>>>,
+        next: Option<Transition>,
+    }
+    impl Logged {
+        fn new(name: &'static str, overlay: bool, log: &Rc<RefCell<Vec<String>>>) -> Box<Logged> {
+            Box::new(Logged {
+                name,
+                overlay,
+                log: log.clone(),
+                next: None,
+            })
+        }
+
+        fn write(&self, event: &str) {
+            self.log
+                .borrow_mut()
+                .push(format!("{} {}", event, self.name));
+        }
+    }
+    impl Object for Logged {
+        fn update(&mut self) {
+            self.write("update");
+        }
+
+        fn draw(&mut self) {
+            self.write("draw");
+        }
+    }
+    impl Scene for Logged {
+        fn enter(&mut self) {
+            self.write("enter");
+        }
+
+        fn exit(&mut self) {
+            self.write("exit");
+        }
+
+        fn transition(&mut self) -> Option<Transition> {
+            self.next.take()
+        }
+
+        fn overlay(&self) -> bool {
+            self.overlay
+        }
+    }
+
+    let log = Rc::new(RefCell::new(vec![]));
+    let take = || log.borrow_mut().drain(..).collect::<Vec<_>>();
+
+    let mut menu = Logged::new("menu", false, &log);
+    menu.next = Some(Transition::Replace(Logged::new("game", false, &log)));
+    let mut stack = SceneStack::new(menu);
+    stack.update();
+    assert_eq!(
+        take(),
+        ["enter menu", "update menu", "exit menu", "enter game"]
+    );
+
+    // Only the top scene gets updated, overlays get drawn over the scenes under them
+    stack.apply(Transition::Push(Logged::new("pause", true, &log)));
+    stack.apply(Transition::Push(Logged::new("dialog", true, &log)));
+    stack.update();
+    stack.draw();
+    assert_eq!(
+        take(),
+        [
+            "enter pause",
+            "enter dialog",
+            "update dialog",
+            "draw game",
+            "draw pause",
+            "draw dialog"
+        ]
+    );
+
+    stack.apply(Transition::Pop);
+    stack.apply(Transition::Push(Logged::new("inventory", false, &log)));
+    stack.draw();
+    assert_eq!(take(), ["exit dialog", "enter inventory", "draw inventory"]);
+    assert_eq!(stack.len(), 3);
+
+    stack.apply(Transition::Replace(Logged::new("victory", false, &log)));
+    assert_eq!(
+        take(),
+        ["exit inventory", "exit pause", "exit game", "enter victory"]
+    );
+    assert_eq!(stack.len(), 1);
+}
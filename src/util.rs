@@ -4,14 +4,11 @@ use std::f32::consts::PI;
 
 use lazy_static::lazy_static;
 use macroquad::prelude::{
-    draw_text, draw_text_ex, measure_text, mouse_position, screen_height, screen_width, vec2,
-    Color, KeyCode, TextParams, Vec2,
+    draw_text, draw_text_ex, measure_text, vec2, Color, KeyCode, TextParams, Vec2,
 };
 use macroquad::rand::gen_range;
 use maplit::hashmap;
 
-use crate::scenes::game::GAME;
-
 pub const NUMBER_KEYS: [KeyCode; 4] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
 pub const DAMAGE_COOLDOWN: f64 = 0.25;
 #[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
@@ -43,6 +40,14 @@ pub const CARDINAL_DIRECTIONS: [(Direction, &str); 4] = [
 ];
 pub const SQUARE_SIZE: f32 = 30.0;
 
+/// World position of the center of a room tile
+pub fn tile_center(tile: (usize, usize)) -> Vec2 {
+    vec2(
+        (tile.0 as f32 + 0.5) * SQUARE_SIZE,
+        (tile.1 as f32 + 0.5) * SQUARE_SIZE,
+    )
+}
+
 lazy_static! {
     pub static ref ROLL_ANGLES: HashMap<Direction, f32> = hashmap! {
         Direction::WA => deg_to_rad(225.0),
@@ -90,52 +95,6 @@ pub fn rad_to_deg(radians: f32) -> f32 {
     radians * 180.0 / PI
 }
 
-/// It returns the x position relative to the screen
-pub fn rx(x: f32) -> f32 {
-    let shake_offset = if GAME().camera.shake.is_none() {
-        0.0
-    } else {
-        GAME().camera.shake_offset.x
-    };
-    return x - (screen_width() / 2.0 - GAME().camera.camera.target.x - shake_offset);
-}
-
-/// It returns the y position relative to the screen
-pub fn ry(y: f32) -> f32 {
-    let shake_offset = if GAME().camera.shake.is_none() {
-        0.0
-    } else {
-        GAME().camera.shake_offset.y
-    };
-    return y - (screen_height() / 2.0 - GAME().camera.camera.target.y - shake_offset);
-}
-
-/// It returns the x position relative to the screen (counteracted to adjust for shake)
-pub fn rx_smooth(x: f32) -> f32 {
-    let shake_offset = if GAME().camera.shake.is_none() {
-        0.0
-    } else {
-        GAME().camera.shake_offset.x
-    };
-    return x - (screen_width() / 2.0 - GAME().camera.camera.target.x + shake_offset);
-}
-
-/// It returns the y position relative to the screen (counteracted to adjust for shake)
-pub fn ry_smooth(y: f32) -> f32 {
-    let shake_offset = if GAME().camera.shake.is_none() {
-        0.0
-    } else {
-        GAME().camera.shake_offset.y
-    };
-    return y - (screen_height() / 2.0 - GAME().camera.camera.target.y + shake_offset);
-}
-
-/// It returns the mouse position relative to the screen
-pub fn rel_mouse_pos() -> Vec2 {
-    let mouse_pos = mouse_position();
-    vec2(rx(mouse_pos.0), ry(mouse_pos.1))
-}
-
 /// It takes two points and returns the distance between them
 pub fn distance(p1: Vec2, p2: Vec2) -> f32 {
     ((p1.x - p2.x).powf(2.0) + (p1.y - p2.y).powf(2.0)).sqrt()
@@ -160,24 +119,6 @@ pub fn multiline_text_ex(text: &str, x: f32, y: f32, params: TextParams) {
     }
 }
 
-#[macro_export]
-/// Crate a global (a static mut) and a public getter function so it can be used safely
-macro_rules! pub_global_variable {
-    ($name: ident, $raw_name: ident, $b: ty) => {
-        static mut $raw_name: Option<$b> = None;
-
-        #[allow(non_snake_case)]
-        pub fn $name() -> &'static mut $b {
-            unsafe {
-                if $raw_name.is_none() {
-                    $raw_name = Some(<$b>::new())
-                }
-                return $raw_name.as_mut().unwrap();
-            }
-        }
-    };
-}
-
 #[macro_export]
 macro_rules! repeat_for_vec {
     ( $function: ident, $( $vector: expr ), * ) => {
@@ -208,6 +149,16 @@ macro_rules! unwrap_or_return {
     };
 }
 
+#[macro_export]
+macro_rules! unwrap_or_continue {
+    ( $option: expr ) => {
+        match $option {
+            Some(value) => value,
+            None => continue,
+        }
+    };
+}
+
 pub fn hex(hex: &'static str) -> Color {
     Color::from_rgba(
         u8::from_str_radix(&hex[1..3], 16).unwrap(),